@@ -0,0 +1,153 @@
+//! Time until the location of a log statement is known, on a large synthetic ELF.
+//!
+//! ``` console
+//! $ cargo bench -p defmt-decoder --features unstable --bench locations
+//! ```
+
+use std::collections::BTreeMap;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use defmt_decoder::{Encoding, StringEntry, Table, TableEntry, Tag};
+use gimli::{
+    constants::{
+        DW_AT_decl_file, DW_AT_decl_line, DW_AT_linkage_name, DW_AT_location, DW_AT_name,
+        DW_TAG_namespace, DW_TAG_subprogram, DW_TAG_variable,
+    },
+    write::{
+        Address, AttributeValue, Dwarf, EndianVec, Expression, LineProgram, LineString, Sections,
+        Unit,
+    },
+    Encoding as DwarfEncoding, Format, LineEncoding, RunTimeEndian,
+};
+use object::{write::Object as ObjectWriter, Architecture, BinaryFormat, Endianness, SectionKind};
+
+/// Compilation units, e.g. one per module of the firmware and its dependencies
+const UNITS: u64 = 1_000;
+const FUNCTIONS_PER_UNIT: u64 = 20;
+/// Functions without a log statement, whose debug info has to be skipped
+const PADDING_PER_FUNCTION: u64 = 10;
+
+fn symbol(address: u64) -> String {
+    format!(r#"{{"package":"app","tag":"defmt_info","data":"{address}","disambiguator":"0"}}"#)
+}
+
+/// Returns an ELF with the debug info of `UNITS * FUNCTIONS_PER_UNIT` log statements, and its table.
+fn elf() -> (Vec<u8>, Table) {
+    let encoding = DwarfEncoding {
+        format: Format::Dwarf32,
+        version: 4,
+        address_size: 4,
+    };
+
+    let mut dwarf = Dwarf::new();
+    let mut entries = BTreeMap::new();
+    for unit_index in 0..UNITS {
+        let module = format!("module{unit_index}");
+        let line_program = LineProgram::new(
+            encoding,
+            LineEncoding::default(),
+            LineString::String(b"/firmware".to_vec()),
+            LineString::String(format!("src/{module}.rs").into_bytes()),
+            None,
+        );
+        let unit_id = dwarf.units.add(Unit::new(encoding, line_program));
+        let unit = dwarf.units.get_mut(unit_id);
+        let directory = unit.line_program.default_directory();
+        let file = unit.line_program.add_file(
+            LineString::String(format!("src/{module}.rs").into_bytes()),
+            directory,
+            None,
+        );
+
+        let root = unit.root();
+        let krate = unit.add(root, DW_TAG_namespace);
+        unit.get_mut(krate)
+            .set(DW_AT_name, AttributeValue::String(b"app".to_vec()));
+        let namespace = unit.add(krate, DW_TAG_namespace);
+        unit.get_mut(namespace)
+            .set(DW_AT_name, AttributeValue::String(module.into_bytes()));
+
+        for function_index in 0..FUNCTIONS_PER_UNIT {
+            let function = format!("function{function_index}");
+            for padding in 0..PADDING_PER_FUNCTION {
+                let id = unit.add(namespace, DW_TAG_subprogram);
+                unit.get_mut(id).set(
+                    DW_AT_name,
+                    AttributeValue::String(format!("helper{function_index}_{padding}").into()),
+                );
+            }
+            let id = unit.add(namespace, DW_TAG_subprogram);
+            unit.get_mut(id)
+                .set(DW_AT_name, AttributeValue::String(function.clone().into()));
+
+            let function_namespace = unit.add(namespace, DW_TAG_namespace);
+            unit.get_mut(function_namespace)
+                .set(DW_AT_name, AttributeValue::String(function.into()));
+
+            let address = unit_index * FUNCTIONS_PER_UNIT + function_index;
+            let mut location = Expression::new();
+            location.op_addr(Address::Constant(address));
+            let variable = unit.add(function_namespace, DW_TAG_variable);
+            let variable = unit.get_mut(variable);
+            variable.set(
+                DW_AT_name,
+                AttributeValue::StringRef(dwarf.strings.add("DEFMT_LOG_STATEMENT")),
+            );
+            variable.set(
+                DW_AT_linkage_name,
+                AttributeValue::StringRef(dwarf.strings.add(symbol(address))),
+            );
+            variable.set(DW_AT_decl_file, AttributeValue::FileIndex(Some(file)));
+            variable.set(DW_AT_decl_line, AttributeValue::Udata(10 + function_index));
+            variable.set(DW_AT_location, AttributeValue::Exprloc(location));
+
+            let entry = TableEntry::new(
+                StringEntry::new(Tag::Info, format!("log statement {address}")),
+                symbol(address),
+            );
+            entries.insert(address as usize, entry);
+        }
+    }
+
+    let mut sections = Sections::new(EndianVec::new(RunTimeEndian::Little));
+    dwarf.write(&mut sections).unwrap();
+
+    let mut object = ObjectWriter::new(BinaryFormat::Elf, Architecture::Arm, Endianness::Little);
+    sections
+        .for_each(|id, data| {
+            let section = object.add_section(vec![], id.name().into(), SectionKind::Debug);
+            object.append_section_data(section, data.slice(), 1);
+            Ok::<_, gimli::write::Error>(())
+        })
+        .unwrap();
+
+    (object.write().unwrap(), Table::new(entries, Encoding::Raw))
+}
+
+fn locations(c: &mut Criterion) {
+    let (elf, table) = elf();
+    let first = 0;
+    let last = UNITS * FUNCTIONS_PER_UNIT - 1;
+
+    let mut group = c.benchmark_group("locations");
+    group.sample_size(20);
+    group.bench_function("all", |b| {
+        b.iter(|| table.get_locations(&elf).unwrap()[&first].line)
+    });
+    group.bench_function("lazy, first unit", |b| {
+        b.iter(|| {
+            let mut locations = table.lazy_locations(&elf).unwrap();
+            locations.get(first).unwrap().unwrap().line
+        })
+    });
+    group.bench_function("lazy, last unit", |b| {
+        b.iter(|| {
+            let mut locations = table.lazy_locations(&elf).unwrap();
+            locations.get(last).unwrap().unwrap().line
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, locations);
+criterion_main!(benches);
@@ -0,0 +1,141 @@
+//! Throughput of the stream decoders, on a capture of many small log frames.
+//!
+//! ``` console
+//! $ cargo bench -p defmt-decoder --features unstable --bench stream
+//! ```
+
+use std::collections::BTreeMap;
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use defmt_decoder::{DecodeError, Encoding, StringEntry, Table, TableEntry, Tag};
+
+const FRAMES: usize = 10_000;
+
+fn table(encoding: Encoding) -> Table {
+    let entries = [
+        (Tag::Info, "boot"),
+        (Tag::Debug, "adc={=u16} temp={=i16}"),
+        (Tag::Info, "packet={=[u8]}"),
+        (Tag::Warn, "addr={=u32:#x} len={=usize}"),
+    ]
+    .into_iter()
+    .enumerate()
+    .map(|(index, (tag, format))| {
+        let entry = TableEntry::new(StringEntry::new(tag, format.into()), "<unknown>".into());
+        (index, entry)
+    })
+    .collect::<BTreeMap<_, _>>();
+    Table::new(entries, encoding)
+}
+
+/// Unencoded log frames, matching the entries of `table`.
+fn frames() -> Vec<Vec<u8>> {
+    (0..FRAMES)
+        .map(|i| {
+            let index = (i % 4) as u16;
+            let mut frame = index.to_le_bytes().to_vec();
+            match index {
+                0 => {}
+                1 => {
+                    frame.extend((i as u16).to_le_bytes());
+                    frame.extend((-3i16).to_le_bytes());
+                }
+                2 => {
+                    let len = i % 32;
+                    frame.extend((len as u32).to_le_bytes());
+                    frame.extend((0..len).map(|x| x as u8));
+                }
+                _ => {
+                    frame.extend((0x2000_0000 + i as u32).to_le_bytes());
+                    frame.extend(12u32.to_le_bytes());
+                }
+            }
+            frame
+        })
+        .collect()
+}
+
+/// Same as the encoder of the `defmt` crate.
+fn rzcobs_encode(frame: &[u8], out: &mut Vec<u8>) {
+    let (mut run, mut zeros) = (0u8, 0u8);
+    for &byte in frame {
+        if run < 7 {
+            if byte == 0 {
+                zeros |= 1 << run;
+            } else {
+                out.push(byte);
+            }
+
+            run += 1;
+            if run == 7 && zeros != 0x00 {
+                out.push(zeros);
+                run = 0;
+                zeros = 0;
+            }
+        } else if byte == 0 {
+            out.push((run - 7) | 0x80);
+            run = 0;
+            zeros = 0;
+        } else {
+            out.push(byte);
+            run += 1;
+            if run == 134 {
+                out.push(0xFF);
+                run = 0;
+                zeros = 0;
+            }
+        }
+    }
+    match run {
+        0 => {}
+        1..=6 => out.push((zeros | (0xFF << run)) & 0x7F),
+        _ => out.push((run - 7) | 0x80),
+    }
+    out.push(0x00);
+}
+
+fn bench(c: &mut Criterion, name: &str, table: &Table, capture: &[u8]) {
+    let mut group = c.benchmark_group(name);
+    group.throughput(Throughput::Bytes(capture.len() as u64));
+
+    for (chunk_name, chunk_size) in [("whole capture", capture.len()), ("64 byte chunks", 64)] {
+        group.bench_function(chunk_name, |b| {
+            b.iter_batched_ref(
+                || table.new_stream_decoder(),
+                |decoder| {
+                    let mut decoded = 0;
+                    for chunk in capture.chunks(chunk_size) {
+                        decoder.received(chunk);
+                        loop {
+                            match decoder.decode() {
+                                Ok(_) => decoded += 1,
+                                Err(DecodeError::UnexpectedEof) => break,
+                                Err(e) => panic!("{e}"),
+                            }
+                        }
+                    }
+                    assert_eq!(decoded, FRAMES);
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+fn raw(c: &mut Criterion) {
+    let capture = frames().concat();
+    bench(c, "raw", &table(Encoding::Raw), &capture);
+}
+
+fn rzcobs(c: &mut Criterion) {
+    let mut capture = vec![0x00];
+    for frame in frames() {
+        rzcobs_encode(&frame, &mut capture);
+    }
+    bench(c, "rzcobs", &table(Encoding::Rzcobs), &capture);
+}
+
+criterion_group!(benches, raw, rzcobs);
+criterion_main!(benches);
@@ -0,0 +1,145 @@
+use defmt_parser::{Fragment, ParserMode, Type};
+
+use crate::{Location, Locations, StringEntry, Table};
+
+/// A log statement of the firmware, see [`Table::catalog`].
+#[derive(Debug)]
+pub struct CatalogEntry<'a> {
+    /// Index of the format string in the table
+    pub index: usize,
+    /// The format string and its level, or [`Tag::Println`](crate::Tag::Println)
+    pub entry: &'a StringEntry,
+    /// The types of the arguments, in the syntax of the format string, e.g. `u8` or `?`
+    pub args: Vec<String>,
+    /// Where the log statement is, if the debug info has it
+    pub location: Option<&'a Location>,
+}
+
+pub(crate) fn catalog<'a>(table: &'a Table, locations: &'a Locations) -> Vec<CatalogEntry<'a>> {
+    let mut catalog = table
+        .indices()
+        .map(|index| {
+            let entry = &table.entries[&index].string;
+            CatalogEntry {
+                index,
+                entry,
+                args: arg_types(&entry.string),
+                location: locations.get(&(index as u64)),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    // log statements of unknown location last
+    catalog.sort_by_key(|entry| {
+        (
+            entry.location.is_none(),
+            entry.location.map(|loc| (&loc.module, &loc.file, loc.line)),
+            entry.index,
+        )
+    });
+    catalog
+}
+
+/// Returns the types of the arguments of `format`, in order of their position.
+fn arg_types(format: &str) -> Vec<String> {
+    let params = match defmt_parser::parse(format, ParserMode::ForwardsCompatible) {
+        Ok(fragments) => fragments.into_iter().filter_map(|frag| match frag {
+            Fragment::Parameter(param) => Some(param),
+            Fragment::Literal(_) => None,
+        }),
+        Err(_) => return vec![],
+    };
+
+    let mut types = Vec::<Option<String>>::new();
+    for param in params {
+        if types.len() <= param.index {
+            types.resize(param.index + 1, None);
+        }
+        types[param.index].get_or_insert_with(|| type_name(&param.ty));
+    }
+    types.into_iter().flatten().collect()
+}
+
+fn type_name(ty: &Type) -> String {
+    let name = match ty {
+        Type::BitField(range) => return format!("{}..{}", range.start, range.end),
+        Type::FormatArray(len) => return format!("[?; {len}]"),
+        Type::U8Array(len) => return format!("[u8; {len}]"),
+        Type::Bool => "bool",
+        Type::Char => "char",
+        Type::Debug => "Debug",
+        Type::Display => "Display",
+        Type::FormatSequence => "?",
+        Type::F32 => "f32",
+        Type::F64 => "f64",
+        Type::Format => "?",
+        Type::FormatSlice => "[?]",
+        Type::I8 => "i8",
+        Type::I16 => "i16",
+        Type::I32 => "i32",
+        Type::I64 => "i64",
+        Type::I128 => "i128",
+        Type::Isize => "isize",
+        Type::IStr => "istr",
+        Type::Str => "str",
+        Type::U8 => "u8",
+        Type::U16 => "u16",
+        Type::U32 => "u32",
+        Type::U64 => "u64",
+        Type::U128 => "u128",
+        Type::Usize => "usize",
+        Type::U8Slice => "[u8]",
+    };
+    name.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::{Encoding, Tag};
+
+    #[test]
+    fn catalog() {
+        let table = Table::with_entries(
+            &[
+                (0, Tag::Warn, "x={=u8} y={} {0=u8}"),
+                (1, Tag::Println, "hello"),
+                (2, Tag::Error, "bits={=0..4} {=[u8; 3]}"),
+                (3, Tag::Derived, "not a log statement"),
+            ],
+            Encoding::Raw,
+        );
+        let location = |module: &str| Location {
+            file: PathBuf::from("src/main.rs"),
+            line: 42,
+            column: None,
+            module: module.to_owned(),
+            function: None,
+        };
+        let mut locations = Locations::new();
+        locations.insert(0, location("app::b"));
+        locations.insert(2, location("app::a"));
+
+        let catalog = table.catalog(&locations);
+        let summary = catalog
+            .iter()
+            .map(|entry| {
+                (
+                    entry.index,
+                    entry.args.join(", "),
+                    entry.location.map(|loc| &*loc.module),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            [
+                (2, "0..4, [u8; 3]".to_owned(), Some("app::a")),
+                (0, "u8, ?".to_owned(), Some("app::b")),
+                (1, "".to_owned(), None),
+            ]
+        );
+    }
+}
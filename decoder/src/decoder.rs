@@ -3,7 +3,7 @@ use std::{
     ops::Range,
 };
 
-use crate::{Arg, DecodeError, FormatSliceElement, Table};
+use crate::{Arg, DecodeError, FormatSliceElement, IntegerEncoding, Table};
 use byteorder::{ReadBytesExt, LE};
 use defmt_parser::{get_max_bitfield_range, Fragment, Parameter, Type};
 
@@ -17,19 +17,47 @@ impl<'t, 'b> Decoder<'t, 'b> {
         Self { table, bytes }
     }
 
-    /// Sort and deduplicate `params` so that they can be interpreted correctly during decoding
-    fn prepare_params(&self, params: &mut Vec<Parameter>) {
-        // deduplicate bitfields by merging them by index
-        merge_bitfields(params);
+    /// Reads an unsigned integer of type `u{bits}` (`bits` >= 16).
+    pub fn read_unsigned(&mut self, bits: u32) -> Result<u128, DecodeError> {
+        match self.table.integer_encoding {
+            IntegerEncoding::Fixed => Ok(match bits {
+                16 => self.bytes.read_u16::<LE>()?.into(),
+                32 => self.bytes.read_u32::<LE>()?.into(),
+                64 => self.bytes.read_u64::<LE>()?.into(),
+                128 => self.bytes.read_u128::<LE>()?,
+                _ => unreachable!(),
+            }),
+            IntegerEncoding::Varint => read_leb128(&mut self.bytes, bits),
+        }
+    }
 
-        // sort & dedup to ensure that format string args can be addressed by index too
-        params.sort_by(|a, b| a.index.cmp(&b.index));
-        params.dedup_by(|a, b| a.index == b.index);
+    /// Reads a signed integer of type `i{bits}` (`bits` >= 16).
+    fn read_signed(&mut self, bits: u32) -> Result<i128, DecodeError> {
+        match self.table.integer_encoding {
+            IntegerEncoding::Fixed => Ok(match bits {
+                16 => self.bytes.read_i16::<LE>()?.into(),
+                32 => self.bytes.read_i32::<LE>()?.into(),
+                64 => self.bytes.read_i64::<LE>()?.into(),
+                128 => self.bytes.read_i128::<LE>()?,
+                _ => unreachable!(),
+            }),
+            IntegerEncoding::Varint => {
+                let zigzag = read_leb128(&mut self.bytes, bits)?;
+                Ok((zigzag >> 1) as i128 ^ -((zigzag & 1) as i128))
+            }
+        }
+    }
+
+    /// Reads a length or interned string index.
+    fn read_usize(&mut self, bits: u32) -> Result<usize, DecodeError> {
+        self.read_unsigned(bits)?
+            .try_into()
+            .map_err(|_| DecodeError::Malformed)
     }
 
     /// Gets a format string from `bytes` and `table`
     fn get_format(&mut self) -> Result<&'t str, DecodeError> {
-        let index = self.bytes.read_u16::<LE>()? as usize;
+        let index = self.read_usize(16)?;
         let format = self
             .table
             .get_without_level(index)
@@ -47,17 +75,11 @@ impl<'t, 'b> Decoder<'t, 'b> {
         let discriminant: usize = if u8::try_from(num_variants).is_ok() {
             self.bytes.read_u8()?.into()
         } else if u16::try_from(num_variants).is_ok() {
-            self.bytes.read_u16::<LE>()?.into()
+            self.read_usize(16)?
         } else if u32::try_from(num_variants).is_ok() {
-            self.bytes
-                .read_u32::<LE>()?
-                .try_into()
-                .map_err(|_| DecodeError::Malformed)?
+            self.read_usize(32)?
         } else if u64::try_from(num_variants).is_ok() {
-            self.bytes
-                .read_u64::<LE>()?
-                .try_into()
-                .map_err(|_| DecodeError::Malformed)?
+            self.read_usize(64)?
         } else {
             return Err(DecodeError::Malformed);
         };
@@ -101,22 +123,22 @@ impl<'t, 'b> Decoder<'t, 'b> {
             })
             .collect::<Vec<_>>();
 
-        self.prepare_params(&mut params);
+        prepare_params(&mut params);
 
         for param in &params {
             match &param.ty {
                 Type::I8 => args.push(Arg::Ixx(self.bytes.read_i8()? as i128)),
-                Type::I16 => args.push(Arg::Ixx(self.bytes.read_i16::<LE>()? as i128)),
-                Type::I32 => args.push(Arg::Ixx(self.bytes.read_i32::<LE>()? as i128)),
-                Type::I64 => args.push(Arg::Ixx(self.bytes.read_i64::<LE>()? as i128)),
-                Type::I128 => args.push(Arg::Ixx(self.bytes.read_i128::<LE>()?)),
-                Type::Isize => args.push(Arg::Ixx(self.bytes.read_i32::<LE>()? as i128)),
+                Type::I16 => args.push(Arg::Ixx(self.read_signed(16)?)),
+                Type::I32 => args.push(Arg::Ixx(self.read_signed(32)?)),
+                Type::I64 => args.push(Arg::Ixx(self.read_signed(64)?)),
+                Type::I128 => args.push(Arg::Ixx(self.read_signed(128)?)),
+                Type::Isize => args.push(Arg::Ixx(self.read_signed(32)?)),
                 Type::U8 => args.push(Arg::Uxx(self.bytes.read_u8()? as u128)),
-                Type::U16 => args.push(Arg::Uxx(self.bytes.read_u16::<LE>()? as u128)),
-                Type::U32 => args.push(Arg::Uxx(self.bytes.read_u32::<LE>()? as u128)),
-                Type::U64 => args.push(Arg::Uxx(self.bytes.read_u64::<LE>()? as u128)),
-                Type::U128 => args.push(Arg::Uxx(self.bytes.read_u128::<LE>()?)),
-                Type::Usize => args.push(Arg::Uxx(self.bytes.read_u32::<LE>()? as u128)),
+                Type::U16 => args.push(Arg::Uxx(self.read_unsigned(16)?)),
+                Type::U32 => args.push(Arg::Uxx(self.read_unsigned(32)?)),
+                Type::U64 => args.push(Arg::Uxx(self.read_unsigned(64)?)),
+                Type::U128 => args.push(Arg::Uxx(self.read_unsigned(128)?)),
+                Type::Usize => args.push(Arg::Uxx(self.read_unsigned(32)?)),
                 Type::F32 => args.push(Arg::F32(f32::from_bits(self.bytes.read_u32::<LE>()?))),
                 Type::F64 => args.push(Arg::F64(f64::from_bits(self.bytes.read_u64::<LE>()?))),
                 Type::Bool => args.push(Arg::Bool(match self.bytes.read_u8()? {
@@ -125,7 +147,7 @@ impl<'t, 'b> Decoder<'t, 'b> {
                     _ => return Err(DecodeError::Malformed),
                 })),
                 Type::FormatSlice => {
-                    let num_elements = self.bytes.read_u32::<LE>()? as usize;
+                    let num_elements = self.read_usize(32)?;
                     let elements = self.decode_format_slice(num_elements)?;
                     args.push(Arg::FormatSlice { elements });
                 }
@@ -155,10 +177,10 @@ impl<'t, 'b> Decoder<'t, 'b> {
 
                     let mut data = match size_after_truncation {
                         1 => self.bytes.read_u8()? as u128,
-                        2 => self.bytes.read_u16::<LE>()? as u128,
-                        3..=4 => self.bytes.read_u32::<LE>()? as u128,
-                        5..=8 => self.bytes.read_u64::<LE>()? as u128,
-                        9..=16 => self.bytes.read_u128::<LE>()?,
+                        2 => self.read_unsigned(16)?,
+                        3..=4 => self.read_unsigned(32)?,
+                        5..=8 => self.read_unsigned(64)?,
+                        9..=16 => self.read_unsigned(128)?,
                         _ => unreachable!(),
                     };
 
@@ -167,7 +189,7 @@ impl<'t, 'b> Decoder<'t, 'b> {
                     args.push(Arg::Uxx(data));
                 }
                 Type::Str => {
-                    let str_len = self.bytes.read_u32::<LE>()? as usize;
+                    let str_len = self.read_usize(32)?;
                     let mut arg_str_bytes = vec![];
 
                     // note: went for the suboptimal but simple solution; optimize if necessary
@@ -182,7 +204,7 @@ impl<'t, 'b> Decoder<'t, 'b> {
                     args.push(Arg::Str(arg_str));
                 }
                 Type::IStr => {
-                    let str_index = self.bytes.read_u16::<LE>()? as usize;
+                    let str_index = self.read_usize(16)?;
 
                     let string = self
                         .table
@@ -193,7 +215,7 @@ impl<'t, 'b> Decoder<'t, 'b> {
                 }
                 Type::U8Slice => {
                     // only supports byte slices
-                    let num_elements = self.bytes.read_u32::<LE>()? as usize;
+                    let num_elements = self.read_usize(32)?;
                     let mut arg_slice = vec![];
 
                     // note: went for the suboptimal but simple solution; optimize if necessary
@@ -236,7 +258,7 @@ impl<'t, 'b> Decoder<'t, 'b> {
                 Type::FormatSequence => {
                     let mut seq_args = Vec::new();
                     loop {
-                        let index = self.bytes.read_u16::<LE>()? as usize;
+                        let index = self.read_usize(16)?;
                         if index == 0 {
                             break;
                         }
@@ -261,6 +283,40 @@ impl<'t, 'b> Decoder<'t, 'b> {
     }
 }
 
+/// Sort and deduplicate `params` so that they can be interpreted correctly during decoding
+pub(crate) fn prepare_params(params: &mut Vec<Parameter>) {
+    // deduplicate bitfields by merging them by index
+    merge_bitfields(params);
+
+    // sort & dedup to ensure that format string args can be addressed by index too
+    params.sort_by(|a, b| a.index.cmp(&b.index));
+    params.dedup_by(|a, b| a.index == b.index);
+}
+
+/// Reads an LEB128-encoded integer of at most `bits` bits.
+fn read_leb128(bytes: &mut &[u8], bits: u32) -> Result<u128, DecodeError> {
+    let mut value = 0u128;
+    let mut shift = 0;
+    loop {
+        let byte = bytes.read_u8()?;
+        let part = u128::from(byte & 0x7f);
+        // reject encodings that are longer than necessary for `bits`, or that overflow a `u128`
+        if shift >= bits || (shift > 128 - 7 && part >> (128 - shift) != 0) {
+            return Err(DecodeError::Malformed);
+        }
+        value |= part << shift;
+        if byte & 0x80 == 0 {
+            break;
+        }
+        shift += 7;
+    }
+
+    if bits < 128 && value >> bits != 0 {
+        return Err(DecodeError::Malformed);
+    }
+    Ok(value)
+}
+
 /// Note that this will not change the Bitfield params in place, i.e. if `params` was sorted before
 /// a call to this function, it won't be afterwards.
 fn merge_bitfields(params: &mut Vec<Parameter>) {
@@ -0,0 +1,197 @@
+use std::collections::BTreeMap;
+
+use crate::{Location, Locations, StringEntry, Table};
+
+/// Comparison of the tables of two builds of the firmware, see [`Table::diff`].
+#[derive(Debug)]
+pub struct TableDiff<'a> {
+    entries: BTreeMap<usize, EntryDiff<'a>>,
+}
+
+/// How the entry at an index changed between two builds of the firmware.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EntryDiff<'a> {
+    /// Both builds have the same format string at this index
+    Unchanged(&'a StringEntry),
+    /// The index is used by both builds, for different format strings
+    Changed {
+        old: &'a StringEntry,
+        new: &'a StringEntry,
+        /// The location of the log statement in the old build, if it is known
+        old_location: Option<&'a Location>,
+        /// The location of the log statement in the new build, if it is known
+        new_location: Option<&'a Location>,
+    },
+    /// Only the new build uses this index
+    Added(&'a StringEntry),
+    /// Only the old build uses this index
+    Removed(&'a StringEntry),
+}
+
+impl<'a> TableDiff<'a> {
+    pub(crate) fn new(old: &'a Table, new: &'a Table) -> Self {
+        let mut entries = BTreeMap::new();
+        for (index, old) in &old.entries {
+            let diff = match new.entries.get(index) {
+                Some(new) if new.string == old.string => EntryDiff::Unchanged(&new.string),
+                Some(new) => EntryDiff::Changed {
+                    old: &old.string,
+                    new: &new.string,
+                    old_location: None,
+                    new_location: None,
+                },
+                None => EntryDiff::Removed(&old.string),
+            };
+            entries.insert(*index, diff);
+        }
+        for (index, new) in &new.entries {
+            entries
+                .entry(*index)
+                .or_insert(EntryDiff::Added(&new.string));
+        }
+
+        Self { entries }
+    }
+
+    /// Adds the locations of the log statements of the old and the new build to the changed
+    /// entries, so that [`TableDiff::is_compatible`] can tell whether their log statements moved.
+    pub fn with_locations(mut self, old: &'a Locations, new: &'a Locations) -> Self {
+        for (index, diff) in &mut self.entries {
+            if let EntryDiff::Changed {
+                old_location,
+                new_location,
+                ..
+            } = diff
+            {
+                *old_location = old.get(&(*index as u64));
+                *new_location = new.get(&(*index as u64));
+            }
+        }
+        self
+    }
+
+    /// Iterates over the indices used by either build, in order.
+    pub fn entries(&self) -> impl Iterator<Item = (usize, EntryDiff<'a>)> + '_ {
+        self.entries.iter().map(|(index, diff)| (*index, *diff))
+    }
+
+    /// Returns `true` if no index was reused for a different format string at the same location,
+    /// i.e. the same file and line.
+    ///
+    /// Otherwise, the new table decodes the logs of devices running the old build incorrectly, and
+    /// the old ELF is still needed to decode them.
+    ///
+    /// If the location of a changed entry is unknown in either build, e.g. because the locations
+    /// were not added with [`TableDiff::with_locations`], it counts as the same location.
+    pub fn is_compatible(&self) -> bool {
+        !self.entries.values().any(EntryDiff::is_incompatible)
+    }
+}
+
+impl EntryDiff<'_> {
+    /// Returns `true` if the index was reused for a different format string at the same location,
+    /// see [`TableDiff::is_compatible`].
+    pub fn is_incompatible(&self) -> bool {
+        match self {
+            EntryDiff::Changed {
+                old_location: Some(old),
+                new_location: Some(new),
+                ..
+            } => old.file == new.file && old.line == new.line,
+            EntryDiff::Changed { .. } => true,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::{Encoding, Tag};
+
+    fn table(entries: &[(usize, Tag, &str)]) -> Table {
+        Table::with_entries(entries, Encoding::Raw)
+    }
+
+    #[test]
+    fn diff() {
+        let old = table(&[
+            (0, Tag::Info, "a"),
+            (1, Tag::Info, "b"),
+            (2, Tag::Info, "c"),
+        ]);
+        let new = table(&[
+            (0, Tag::Info, "a"),
+            (1, Tag::Warn, "b"),
+            (3, Tag::Info, "d"),
+        ]);
+        let entry = |tag, string: &str| StringEntry::new(tag, string.to_string());
+
+        let diff = old.diff(&new);
+        assert_eq!(
+            diff.entries().collect::<Vec<_>>(),
+            [
+                (0, EntryDiff::Unchanged(&entry(Tag::Info, "a"))),
+                (
+                    1,
+                    EntryDiff::Changed {
+                        old: &entry(Tag::Info, "b"),
+                        new: &entry(Tag::Warn, "b"),
+                        old_location: None,
+                        new_location: None,
+                    }
+                ),
+                (2, EntryDiff::Removed(&entry(Tag::Info, "c"))),
+                (3, EntryDiff::Added(&entry(Tag::Info, "d"))),
+            ]
+        );
+        assert!(!diff.is_compatible());
+        assert!(old.diff(&old).is_compatible());
+    }
+
+    #[test]
+    fn locations() {
+        let old = table(&[(0, Tag::Info, "a"), (1, Tag::Info, "b")]);
+        let new = table(&[(0, Tag::Warn, "a"), (1, Tag::Info, "c")]);
+        let location = |line| Location {
+            file: PathBuf::from("src/main.rs"),
+            line,
+            column: None,
+            module: "app".to_owned(),
+            function: None,
+        };
+        let old_locations = Locations::from([(0, location(10)), (1, location(20))]);
+
+        // index 0 stays at line 10, but the statement at line 20 moved to line 30
+        let new_locations = Locations::from([(0, location(10)), (1, location(30))]);
+        let diff = old
+            .diff(&new)
+            .with_locations(&old_locations, &new_locations);
+        assert_eq!(
+            diff.entries()
+                .map(|(_, diff)| diff.is_incompatible())
+                .collect::<Vec<_>>(),
+            [true, false]
+        );
+        assert!(!diff.is_compatible());
+
+        let new_locations = Locations::from([(0, location(11)), (1, location(30))]);
+        let diff = old
+            .diff(&new)
+            .with_locations(&old_locations, &new_locations);
+        assert!(diff.is_compatible());
+        let (_, changed) = diff.entries().next().unwrap();
+        let EntryDiff::Changed {
+            old_location,
+            new_location,
+            ..
+        } = changed
+        else {
+            panic!("unexpected {changed:?}");
+        };
+        assert_eq!(old_location, Some(&location(10)));
+        assert_eq!(new_location, Some(&location(11)));
+    }
+}
@@ -0,0 +1,122 @@
+//! Lookup of separate debug info files, like `objcopy --only-keep-debug` creates.
+//!
+//! The files are searched for in the same places as GDB does by default, see
+//! <https://sourceware.org/gdb/onlinedocs/gdb/Separate-Debug-Files.html>.
+
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+use object::Object;
+
+/// The global debug directory
+const DEBUG_DIRECTORY: &str = "/usr/lib/debug";
+
+/// Returns the path of the separate debug info file of `elf`, which was read from `elf_path`.
+///
+/// The file is looked up by the build ID of `elf`, and then by the name in its `.gnu_debuglink`
+/// section. Returns `None` if `elf` has debug info of its own, or if no debug info file is found.
+pub fn find_debug_file(elf: &[u8], elf_path: &Path) -> Option<PathBuf> {
+    let object = object::File::parse(elf).ok()?;
+    if object.section_by_name(".debug_info").is_some() {
+        return None;
+    }
+    let dir = elf_path.parent().unwrap_or_else(|| Path::new(""));
+
+    if let Ok(Some(build_id)) = object.build_id() {
+        let found = build_id_candidates(dir, build_id)
+            .into_iter()
+            .find(|path| path.is_file());
+        if found.is_some() {
+            return found;
+        }
+    }
+
+    if let Ok(Some((name, crc))) = object.gnu_debuglink() {
+        let name = std::str::from_utf8(name).ok()?;
+        return debuglink_candidates(dir, name)
+            .into_iter()
+            .filter(|path| path != elf_path)
+            .find(|path| fs::read(path).is_ok_and(|data| crc32(&data) == crc));
+    }
+
+    None
+}
+
+/// `.build-id/ab/cdef...debug`, for the build ID `abcdef...`, in `dir` and the global debug directory
+fn build_id_candidates(dir: &Path, build_id: &[u8]) -> Vec<PathBuf> {
+    let (first, rest) = match build_id.split_first() {
+        Some(split) => split,
+        None => return vec![],
+    };
+    let hex = |bytes: &[u8]| bytes.iter().map(|b| format!("{b:02x}")).collect::<String>();
+    let relative = Path::new(".build-id")
+        .join(hex(&[*first]))
+        .join(format!("{}.debug", hex(rest)));
+
+    vec![
+        dir.join(&relative),
+        Path::new(DEBUG_DIRECTORY).join(relative),
+    ]
+}
+
+/// `name` next to the ELF, in its `.debug` subdirectory and in the global debug directory
+fn debuglink_candidates(dir: &Path, name: &str) -> Vec<PathBuf> {
+    let mut global = PathBuf::from(DEBUG_DIRECTORY);
+    // `join` would replace the debug directory with an absolute `dir`
+    global.extend(
+        dir.components()
+            .filter(|component| matches!(component, Component::Normal(_))),
+    );
+
+    vec![
+        dir.join(name),
+        dir.join(".debug").join(name),
+        global.join(name),
+    ]
+}
+
+/// The CRC-32 `.gnu_debuglink` uses to identify the debug info file
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = match crc & 1 {
+                0 => crc >> 1,
+                _ => (crc >> 1) ^ 0xedb8_8320,
+            };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_value() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+    }
+
+    #[test]
+    fn candidates() {
+        assert_eq!(
+            build_id_candidates(Path::new("/fw"), &[0xab, 0xcd, 0xef]),
+            [
+                PathBuf::from("/fw/.build-id/ab/cdef.debug"),
+                PathBuf::from("/usr/lib/debug/.build-id/ab/cdef.debug"),
+            ]
+        );
+        assert_eq!(
+            debuglink_candidates(Path::new("/fw/release"), "app.debug"),
+            [
+                PathBuf::from("/fw/release/app.debug"),
+                PathBuf::from("/fw/release/.debug/app.debug"),
+                PathBuf::from("/usr/lib/debug/fw/release/app.debug"),
+            ]
+        );
+    }
+}
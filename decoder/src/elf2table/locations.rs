@@ -0,0 +1,463 @@
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashSet},
+    fmt,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{anyhow, bail, ensure};
+use gimli::Reader as _;
+use object::{Object, ObjectSection};
+
+use crate::Table;
+
+/// Location of a defmt log statement in the elf-file
+#[derive(Clone, PartialEq, Eq)]
+pub struct Location {
+    pub file: PathBuf,
+    pub line: u64,
+    /// The column, if the debug info records it
+    pub column: Option<u64>,
+    pub module: String,
+    /// Qualified name of the function that contains the log statement, if it is known
+    pub function: Option<String>,
+}
+
+impl fmt::Debug for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.file.display(), self.line)?;
+        if let Some(column) = self.column {
+            write!(f, ":{column}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Mapping of memory address to [`Location`]
+pub type Locations = BTreeMap<u64, Location>;
+
+/// The locations of the log statements, which are looked up in the debug info on first use.
+///
+/// Parsing all of the debug info of a large ELF takes a while, so the compilation units are parsed
+/// one at a time, and only until the location that is asked for is found. The locations of all log
+/// statements in a parsed unit are cached.
+pub struct LazyLocations {
+    dwarf: gimli::Dwarf<Reader>,
+    /// The units that have not been parsed yet
+    units: gimli::DebugInfoUnitHeadersIter<Reader>,
+    /// Raw symbols of the log statements, which are the linkage names of their statics
+    symbols: HashSet<String>,
+    locations: Locations,
+}
+
+type Reader = gimli::EndianArcSlice<gimli::RunTimeEndian>;
+
+impl LazyLocations {
+    pub(crate) fn new(elf: &[u8], table: &Table) -> Result<Self, anyhow::Error> {
+        let object = object::File::parse(elf)?;
+        let endian = if object.is_little_endian() {
+            gimli::RunTimeEndian::Little
+        } else {
+            gimli::RunTimeEndian::Big
+        };
+
+        let load_section = |id: gimli::SectionId| -> Result<Reader, anyhow::Error> {
+            let data = match object.section_by_name(id.name()) {
+                Some(s) => s.uncompressed_data().unwrap_or(Cow::Borrowed(&[][..])),
+                None => Cow::Borrowed(&[][..]),
+            };
+            Ok(gimli::EndianArcSlice::new(Arc::from(&*data), endian))
+        };
+        let dwarf = gimli::Dwarf::load(load_section)?;
+
+        let symbols = table
+            .indices()
+            .map(|index| table.entries[&index].raw_symbol.clone())
+            .collect();
+
+        Ok(Self {
+            units: dwarf.units(),
+            dwarf,
+            symbols,
+            locations: Locations::new(),
+        })
+    }
+
+    /// Returns the location of the log statement with the given `index`, if it is known.
+    pub fn get(&mut self, index: u64) -> Result<Option<&Location>, anyhow::Error> {
+        while !self.locations.contains_key(&index) && self.locations.len() < self.symbols.len() {
+            match self.units.next()? {
+                Some(header) => self.parse_unit(header)?,
+                None => break,
+            }
+        }
+
+        Ok(self.locations.get(&index))
+    }
+
+    /// Looks up all locations.
+    pub fn resolve_all(mut self) -> Result<Locations, anyhow::Error> {
+        while self.locations.len() < self.symbols.len() {
+            match self.units.next()? {
+                Some(header) => self.parse_unit(header)?,
+                None => break,
+            }
+        }
+
+        Ok(self.locations)
+    }
+
+    fn parse_unit(&mut self, header: gimli::UnitHeader<Reader>) -> Result<(), anyhow::Error> {
+        let dwarf = &self.dwarf;
+        let unit = dwarf.unit(header)?;
+
+        let mut cursor = unit.entries();
+
+        ensure!(cursor.next_dfs()?.is_some(), "empty DWARF?");
+
+        // qualified names of the functions in this unit, to find the function a log statement is in
+        let mut functions = HashSet::new();
+        // log statements which are not nested in a function entry
+        let mut unresolved = vec![];
+        // the namespaces and functions enclosing the current entry, with their depth
+        let mut scopes: Vec<(isize, Scope)> = vec![];
+        let mut depth = 0;
+        while let Some((delta_depth, entry)) = cursor.next_dfs()? {
+            depth += delta_depth;
+            while matches!(scopes.last(), Some((scope_depth, _)) if *scope_depth >= depth) {
+                scopes.pop();
+            }
+
+            // NOTE .. here start the custom logic
+            if entry.tag() == gimli::constants::DW_TAG_namespace {
+                if let Some(name) = entry.attr_value(gimli::constants::DW_AT_name)? {
+                    let name = dwarf.attr_string(&unit, name)?.to_string()?.into_owned();
+                    scopes.push((depth, Scope::Namespace(name)));
+                }
+            } else if entry.tag() == gimli::constants::DW_TAG_subprogram {
+                if let Some(name) = entry.attr_value(gimli::constants::DW_AT_name)? {
+                    let name = dwarf.attr_string(&unit, name)?;
+                    let name = name.to_string()?;
+                    // generic functions are named with their parameters, e.g. `read<u8>`
+                    let name = name.split('<').next().unwrap_or(&name);
+
+                    let mut path = namespaces(&scopes);
+                    path.push(name);
+                    let function = path.join("::");
+                    functions.insert(function.clone());
+                    scopes.push((depth, Scope::Function(function)));
+                }
+            } else if entry.tag() == gimli::constants::DW_TAG_variable {
+                // Iterate over the attributes in the DIE.
+                let mut attrs = entry.attrs();
+
+                // what we are after
+                let mut decl_file = None;
+                let mut decl_line = None; // line number
+                let mut decl_column = None;
+                let mut name = None;
+                let mut linkage_name = None;
+                let mut location = None;
+
+                while let Some(attr) = attrs.next()? {
+                    match attr.name() {
+                        gimli::constants::DW_AT_name => {
+                            if let gimli::AttributeValue::DebugStrRef(off) = attr.value() {
+                                name = Some(off);
+                            }
+                        }
+                        gimli::constants::DW_AT_decl_file => {
+                            if let gimli::AttributeValue::FileIndex(idx) = attr.value() {
+                                decl_file = Some(idx);
+                            }
+                        }
+                        gimli::constants::DW_AT_decl_line => {
+                            if let gimli::AttributeValue::Udata(line) = attr.value() {
+                                decl_line = Some(line);
+                            }
+                        }
+                        gimli::constants::DW_AT_decl_column => {
+                            if let gimli::AttributeValue::Udata(column) = attr.value() {
+                                decl_column = Some(column);
+                            }
+                        }
+                        gimli::constants::DW_AT_location => {
+                            if let gimli::AttributeValue::Exprloc(loc) = attr.value() {
+                                location = Some(loc);
+                            }
+                        }
+                        gimli::constants::DW_AT_linkage_name => {
+                            if let gimli::AttributeValue::DebugStrRef(off) = attr.value() {
+                                linkage_name = Some(off);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+
+                if let (
+                    Some(name_index),
+                    Some(linkage_name_index),
+                    Some(file_index),
+                    Some(line),
+                    Some(loc),
+                ) = (name, linkage_name, decl_file, decl_line, location)
+                {
+                    let name = dwarf.string(name_index)?;
+
+                    if name.to_string()? == "DEFMT_LOG_STATEMENT" {
+                        let linkage_name = dwarf.string(linkage_name_index)?;
+
+                        if self.symbols.contains(&*linkage_name.to_string()?) {
+                            let addr = exprloc2address(unit.encoding(), &loc)?;
+                            let file = file_index_to_path(file_index, &unit, dwarf)?;
+                            let module = namespaces(&scopes).join("::");
+                            let function = scopes.iter().rev().find_map(|(_, scope)| match scope {
+                                Scope::Function(function) => Some(function.clone()),
+                                Scope::Namespace(_) => None,
+                            });
+
+                            if function.is_none() {
+                                unresolved.push(addr);
+                            }
+                            let loc = Location {
+                                file,
+                                line,
+                                column: decl_column,
+                                module,
+                                function,
+                            };
+
+                            if let Some(old) = self.locations.insert(addr, loc.clone()) {
+                                bail!("BUG in DWARF variable filter: index collision for addr 0x{:08x} (old = {:?}, new = {:?})", addr, old, loc);
+                            }
+                        } else {
+                            // this symbol was GC-ed by the linker (but remains in the DWARF info)
+                            // so we discard it (its `addr` info is also wrong which causes collisions)
+                        }
+                    }
+                }
+            }
+        }
+
+        // rustc puts statics that are declared in a function in a namespace named after the
+        // function, instead of in the function entry: look for the innermost namespace that is a
+        // function
+        for addr in unresolved {
+            let loc = self.locations.get_mut(&addr).unwrap();
+            let mut path = loc.module.split("::").collect::<Vec<_>>();
+            while !path.is_empty() {
+                let function = path.join("::");
+                if functions.contains(&function) {
+                    loc.function = Some(function);
+                    break;
+                }
+                path.pop();
+            }
+        }
+
+        Ok(())
+    }
+}
+
+enum Scope {
+    Namespace(String),
+    /// A function, with its qualified name
+    Function(String),
+}
+
+/// Returns the names of the namespaces in `scopes` that are not nested in a function.
+fn namespaces(scopes: &[(isize, Scope)]) -> Vec<&str> {
+    scopes
+        .iter()
+        .map_while(|(_, scope)| match scope {
+            Scope::Namespace(name) => Some(&**name),
+            Scope::Function(_) => None,
+        })
+        .collect()
+}
+
+fn file_index_to_path<R>(
+    index: u64,
+    unit: &gimli::Unit<R>,
+    dwarf: &gimli::Dwarf<R>,
+) -> Result<PathBuf, anyhow::Error>
+where
+    R: gimli::read::Reader,
+{
+    ensure!(index != 0, "`FileIndex` was zero");
+
+    let header = if let Some(program) = &unit.line_program {
+        program.header()
+    } else {
+        bail!("no `LineProgram`");
+    };
+
+    let file = if let Some(file) = header.file(index) {
+        file
+    } else {
+        bail!("no `FileEntry` for index {}", index)
+    };
+
+    let mut p = PathBuf::new();
+    if let Some(dir) = file.directory(header) {
+        let dir = dwarf.attr_string(unit, dir)?;
+        let dir_s = dir.to_string_lossy()?;
+        let dir = Path::new(&dir_s[..]);
+
+        if !dir.is_absolute() {
+            if let Some(ref comp_dir) = unit.comp_dir {
+                p.push(&comp_dir.to_string_lossy()?[..]);
+            }
+        }
+        p.push(dir);
+    }
+
+    p.push(
+        &dwarf
+            .attr_string(unit, file.path_name())?
+            .to_string_lossy()?[..],
+    );
+
+    Ok(p)
+}
+
+fn exprloc2address<R: gimli::read::Reader<Offset = usize>>(
+    encoding: gimli::Encoding,
+    data: &gimli::Expression<R>,
+) -> Result<u64, anyhow::Error> {
+    let mut pc = data.0.clone();
+    while pc.len() != 0 {
+        if let Ok(gimli::Operation::Address { address }) =
+            gimli::Operation::parse(&mut pc, encoding)
+        {
+            return Ok(address);
+        }
+    }
+
+    Err(anyhow!("`Operation::Address` not found"))
+}
+
+#[cfg(test)]
+mod tests {
+    use gimli::{
+        constants::*,
+        write::{self, Address, AttributeValue, EndianVec, Expression, LineProgram, LineString},
+        Format, LineEncoding, RunTimeEndian,
+    };
+    use object::{write::Object as ObjectWriter, Architecture, BinaryFormat, Endianness};
+
+    use super::*;
+    use crate::{Encoding, StringEntry, TableEntry, Tag};
+
+    /// Returns an ELF with one unit per module, with a log statement in function `f` of each
+    /// module, and its table
+    fn elf(modules: &[&str]) -> (Vec<u8>, Table) {
+        let encoding = gimli::Encoding {
+            format: Format::Dwarf32,
+            version: 4,
+            address_size: 4,
+        };
+
+        let mut dwarf = write::Dwarf::new();
+        let mut entries = BTreeMap::new();
+        for (address, module) in modules.iter().enumerate() {
+            let line_program = LineProgram::new(
+                encoding,
+                LineEncoding::default(),
+                LineString::String(b"/fw".to_vec()),
+                LineString::String(b"src/lib.rs".to_vec()),
+                None,
+            );
+            let unit = dwarf.units.add(write::Unit::new(encoding, line_program));
+            let unit = dwarf.units.get_mut(unit);
+            let directory = unit.line_program.default_directory();
+            let file = unit.line_program.add_file(
+                LineString::String(format!("src/{module}.rs").into_bytes()),
+                directory,
+                None,
+            );
+
+            let mut parent = unit.root();
+            for (tag, name) in [
+                (DW_TAG_namespace, "app"),
+                (DW_TAG_namespace, module),
+                (DW_TAG_subprogram, "f<u8>"),
+            ] {
+                let id = unit.add(parent, tag);
+                unit.get_mut(id)
+                    .set(DW_AT_name, AttributeValue::String(name.into()));
+                // the log statement goes in a namespace next to the function
+                if tag == DW_TAG_namespace {
+                    parent = id;
+                }
+            }
+            let namespace = unit.add(parent, DW_TAG_namespace);
+            unit.get_mut(namespace)
+                .set(DW_AT_name, AttributeValue::String(b"f".to_vec()));
+
+            let symbol = format!("symbol{address}");
+            let mut location = Expression::new();
+            location.op_addr(Address::Constant(address as u64));
+            let variable = unit.add(namespace, DW_TAG_variable);
+            let variable = unit.get_mut(variable);
+            variable.set(
+                DW_AT_name,
+                AttributeValue::StringRef(dwarf.strings.add("DEFMT_LOG_STATEMENT")),
+            );
+            variable.set(
+                DW_AT_linkage_name,
+                AttributeValue::StringRef(dwarf.strings.add(&*symbol)),
+            );
+            variable.set(DW_AT_decl_file, AttributeValue::FileIndex(Some(file)));
+            variable.set(DW_AT_decl_line, AttributeValue::Udata(42));
+            variable.set(DW_AT_decl_column, AttributeValue::Udata(5));
+            variable.set(DW_AT_location, AttributeValue::Exprloc(location));
+
+            let string = StringEntry::new(Tag::Info, "hello".to_owned());
+            entries.insert(address, TableEntry::new(string, symbol));
+        }
+
+        let mut sections = write::Sections::new(EndianVec::new(RunTimeEndian::Little));
+        dwarf.write(&mut sections).unwrap();
+        let mut object =
+            ObjectWriter::new(BinaryFormat::Elf, Architecture::Arm, Endianness::Little);
+        sections
+            .for_each(|id, data| {
+                let section =
+                    object.add_section(vec![], id.name().into(), object::SectionKind::Debug);
+                object.append_section_data(section, data.slice(), 1);
+                Ok::<_, write::Error>(())
+            })
+            .unwrap();
+
+        (object.write().unwrap(), Table::new(entries, Encoding::Raw))
+    }
+
+    #[test]
+    fn lazy() {
+        let (elf, table) = elf(&["a", "b", "c"]);
+        let mut locations = table.lazy_locations(&elf).unwrap();
+
+        let location = locations.get(1).unwrap().unwrap();
+        assert_eq!(location.file, Path::new("src/b.rs"));
+        assert_eq!((location.line, location.column), (42, Some(5)));
+        assert_eq!(location.module, "app::b::f");
+        assert_eq!(location.function.as_deref(), Some("app::b::f"));
+        // the unit of `c` has not been parsed yet
+        assert_eq!(locations.locations.len(), 2);
+
+        assert!(locations.get(3).unwrap().is_none());
+        assert_eq!(locations.locations.len(), 3);
+    }
+
+    #[test]
+    fn all() {
+        let (elf, table) = elf(&["a", "b"]);
+        let locations = table.get_locations(&elf).unwrap();
+
+        assert_eq!(locations.len(), 2);
+        assert_eq!(locations[&0].file, Path::new("src/a.rs"));
+    }
+}
@@ -3,25 +3,28 @@
 //! This is an implementation detail of [`probe-run`](https://github.com/knurling-rs/probe-run) and
 //! not meant to be consumed by other tools at the moment so all the API is unstable.
 
+mod debug_file;
+mod locations;
 mod symbol;
 
 use std::{
-    borrow::Cow,
     collections::{BTreeMap, HashMap},
     convert::TryInto,
-    fmt,
-    path::{Path, PathBuf},
 };
 
-use crate::{BitflagsKey, StringEntry, Table, TableEntry, Tag, DEFMT_VERSION};
-use anyhow::{anyhow, bail, ensure};
+use crate::{BitflagsKey, IntegerEncoding, StringEntry, Table, TableEntry, Tag, DEFMT_VERSION};
+use anyhow::{anyhow, bail};
 use object::{Object, ObjectSection, ObjectSymbol};
 
+pub use debug_file::find_debug_file;
+pub use locations::{LazyLocations, Location, Locations};
+
 pub fn parse_impl(elf: &[u8], check_version: bool) -> Result<Option<Table>, anyhow::Error> {
     let elf = object::File::parse(elf)?;
     // first pass to extract the `_defmt_version`
     let mut version = None;
     let mut encoding = None;
+    let mut image_id = None;
 
     // Note that we check for a quoted and unquoted version symbol, since LLD has a bug that
     // makes it keep the quotes from the linker script.
@@ -51,6 +54,11 @@ pub fn parse_impl(elf: &[u8], check_version: bool) -> Result<Option<Table>, anyh
             Err(_) => continue,
         };
 
+        // The address of this function identifies the image, see the `image-id` feature of `defmt`
+        if name == "_defmt_image_id" {
+            image_id = Some(entry.address() as u32);
+        }
+
         // Not in the `.defmt` section because it's not tied to the address of any symbol
         // in `.defmt`.
         if let Some(new_version) = try_get_version(name) {
@@ -98,8 +106,16 @@ pub fn parse_impl(elf: &[u8], check_version: bool) -> Result<Option<Table>, anyh
         self::check_version(&version).map_err(anyhow::Error::msg)?;
     }
 
-    let encoding = match encoding {
-        Some(e) => e.parse()?,
+    let (encoding, integer_encoding) = match encoding {
+        // e.g. `rzcobs+varint`
+        Some(e) => match e.split_once('+') {
+            Some((e, "varint")) => (e.parse()?, IntegerEncoding::Varint),
+            Some((_, modifier)) => bail!(
+                "Unknown defmt encoding modifier '{}' specified. This is a bug.",
+                modifier
+            ),
+            None => (e.parse()?, IntegerEncoding::Fixed),
+        },
         None => bail!("No defmt encoding specified. This is a bug."),
     };
 
@@ -212,6 +228,8 @@ pub fn parse_impl(elf: &[u8], check_version: bool) -> Result<Option<Table>, anyh
         timestamp,
         bitflags,
         encoding,
+        integer_encoding,
+        image_id,
     }))
 }
 
@@ -229,217 +247,21 @@ fn check_version(version: &str) -> Result<(), String> {
     Ok(())
 }
 
-/// Location of a defmt log statement in the elf-file
-#[derive(Clone)]
-pub struct Location {
-    pub file: PathBuf,
-    pub line: u64,
-    pub module: String,
-}
-
-impl fmt::Debug for Location {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}:{}", self.file.display(), self.line)
-    }
-}
-
-/// Mapping of memory address to [`Location`]
-pub type Locations = BTreeMap<u64, Location>;
-
-pub fn get_locations(elf: &[u8], table: &Table) -> Result<Locations, anyhow::Error> {
-    let object = object::File::parse(elf)?;
-    let endian = if object.is_little_endian() {
-        gimli::RunTimeEndian::Little
-    } else {
-        gimli::RunTimeEndian::Big
-    };
-
-    let load_section = |id: gimli::SectionId| {
-        Ok(if let Some(s) = object.section_by_name(id.name()) {
-            s.uncompressed_data().unwrap_or(Cow::Borrowed(&[][..]))
-        } else {
-            Cow::Borrowed(&[][..])
-        })
-    };
-    let load_section_sup = |_| Ok(Cow::Borrowed(&[][..]));
-
-    let mut dwarf_cow = gimli::Dwarf::<Cow<[u8]>>::load::<_, anyhow::Error>(&load_section)?;
-    dwarf_cow.load_sup::<_, anyhow::Error>(&load_section_sup)?;
-
-    let borrow_section: &dyn for<'a> Fn(
-        &'a Cow<[u8]>,
-    ) -> gimli::EndianSlice<'a, gimli::RunTimeEndian> =
-        &|section| gimli::EndianSlice::new(section, endian);
-
-    let dwarf = dwarf_cow.borrow(&borrow_section);
-
-    let mut units = dwarf.debug_info.units();
-
-    let mut map = BTreeMap::new();
-    while let Some(header) = units.next()? {
-        let unit = dwarf.unit(header)?;
-        let abbrev = header.abbreviations(&dwarf.debug_abbrev)?;
-
-        let mut cursor = header.entries(&abbrev);
-
-        ensure!(cursor.next_dfs()?.is_some(), "empty DWARF?");
-
-        let mut segments = vec![];
-        let mut depth = 0;
-        while let Some((delta_depth, entry)) = cursor.next_dfs()? {
-            depth += delta_depth;
-
-            // NOTE .. here start the custom logic
-            if entry.tag() == gimli::constants::DW_TAG_namespace {
-                let mut attrs = entry.attrs();
-
-                while let Some(attr) = attrs.next()? {
-                    if attr.name() == gimli::constants::DW_AT_name {
-                        if let gimli::AttributeValue::DebugStrRef(off) = attr.value() {
-                            let s = dwarf.string(off)?;
-                            for _ in (depth as usize)..segments.len() + 1 {
-                                segments.pop();
-                            }
-                            segments.push(core::str::from_utf8(&s)?.to_string());
-                        }
-                    }
-                }
-            } else if entry.tag() == gimli::constants::DW_TAG_variable {
-                // Iterate over the attributes in the DIE.
-                let mut attrs = entry.attrs();
-
-                // what we are after
-                let mut decl_file = None;
-                let mut decl_line = None; // line number
-                let mut name = None;
-                let mut linkage_name = None;
-                let mut location = None;
-
-                while let Some(attr) = attrs.next()? {
-                    match attr.name() {
-                        gimli::constants::DW_AT_name => {
-                            if let gimli::AttributeValue::DebugStrRef(off) = attr.value() {
-                                name = Some(off);
-                            }
-                        }
-                        gimli::constants::DW_AT_decl_file => {
-                            if let gimli::AttributeValue::FileIndex(idx) = attr.value() {
-                                decl_file = Some(idx);
-                            }
-                        }
-                        gimli::constants::DW_AT_decl_line => {
-                            if let gimli::AttributeValue::Udata(line) = attr.value() {
-                                decl_line = Some(line);
-                            }
-                        }
-                        gimli::constants::DW_AT_location => {
-                            if let gimli::AttributeValue::Exprloc(loc) = attr.value() {
-                                location = Some(loc);
-                            }
-                        }
-                        gimli::constants::DW_AT_linkage_name => {
-                            if let gimli::AttributeValue::DebugStrRef(off) = attr.value() {
-                                linkage_name = Some(off);
-                            }
-                        }
-                        _ => {}
-                    }
-                }
-
-                if let (
-                    Some(name_index),
-                    Some(linkage_name_index),
-                    Some(file_index),
-                    Some(line),
-                    Some(loc),
-                ) = (name, linkage_name, decl_file, decl_line, location)
-                {
-                    let name_slice = dwarf.string(name_index)?;
-                    let name = core::str::from_utf8(&name_slice)?;
-                    let linkage_name_slice = dwarf.string(linkage_name_index)?;
-                    let linkage_name = core::str::from_utf8(&linkage_name_slice)?;
-
-                    if name == "DEFMT_LOG_STATEMENT" {
-                        if table.raw_symbols().any(|i| i == linkage_name) {
-                            let addr = exprloc2address(unit.encoding(), &loc)?;
-                            let file = file_index_to_path(file_index, &unit, &dwarf)?;
-                            let module = segments.join("::");
-
-                            let loc = Location { file, line, module };
-
-                            if let Some(old) = map.insert(addr, loc.clone()) {
-                                bail!("BUG in DWARF variable filter: index collision for addr 0x{:08x} (old = {:?}, new = {:?})", addr, old, loc);
-                            }
-                        } else {
-                            // this symbol was GC-ed by the linker (but remains in the DWARF info)
-                            // so we discard it (its `addr` info is also wrong which causes collisions)
-                        }
-                    }
-                }
-            }
-        }
-    }
-
-    Ok(map)
-}
-
-fn file_index_to_path<R>(
-    index: u64,
-    unit: &gimli::Unit<R>,
-    dwarf: &gimli::Dwarf<R>,
-) -> Result<PathBuf, anyhow::Error>
-where
-    R: gimli::read::Reader,
-{
-    ensure!(index != 0, "`FileIndex` was zero");
-
-    let header = if let Some(program) = &unit.line_program {
-        program.header()
-    } else {
-        bail!("no `LineProgram`");
-    };
-
-    let file = if let Some(file) = header.file(index) {
-        file
-    } else {
-        bail!("no `FileEntry` for index {}", index)
-    };
-
-    let mut p = PathBuf::new();
-    if let Some(dir) = file.directory(header) {
-        let dir = dwarf.attr_string(unit, dir)?;
-        let dir_s = dir.to_string_lossy()?;
-        let dir = Path::new(&dir_s[..]);
-
-        if !dir.is_absolute() {
-            if let Some(ref comp_dir) = unit.comp_dir {
-                p.push(&comp_dir.to_string_lossy()?[..]);
-            }
+/// Returns the sizes of the functions whose name starts with `_defmt_`, like `_defmt_write`.
+///
+/// These are the functions of the global logger, which all log statements call.
+pub fn defmt_function_sizes(elf: &[u8]) -> Result<BTreeMap<String, u64>, anyhow::Error> {
+    let elf = object::File::parse(elf)?;
+    let mut sizes = BTreeMap::new();
+    for symbol in elf.symbols() {
+        if symbol.kind() != object::SymbolKind::Text {
+            continue;
         }
-        p.push(dir);
-    }
-
-    p.push(
-        &dwarf
-            .attr_string(unit, file.path_name())?
-            .to_string_lossy()?[..],
-    );
-
-    Ok(p)
-}
-
-fn exprloc2address<R: gimli::read::Reader<Offset = usize>>(
-    encoding: gimli::Encoding,
-    data: &gimli::Expression<R>,
-) -> Result<u64, anyhow::Error> {
-    let mut pc = data.0.clone();
-    while pc.len() != 0 {
-        if let Ok(gimli::Operation::Address { address }) =
-            gimli::Operation::parse(&mut pc, encoding)
-        {
-            return Ok(address);
+        let name = symbol.name()?;
+        if name.starts_with("_defmt_") {
+            sizes.insert(name.to_string(), symbol.size());
         }
     }
 
-    Err(anyhow!("`Operation::Address` not found"))
+    Ok(sizes)
 }
@@ -0,0 +1,266 @@
+//! Conditions to stop decoding on, for tools that run in CI.
+
+use std::{
+    io,
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::anyhow;
+pub use defmt_parser::Level;
+use regex::Regex;
+
+use crate::Frame;
+
+/// Why decoding stopped early, or the input ended without a match, see [`ExitConditions`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExitReason {
+    /// The message of a frame matched the pattern
+    Matched,
+    /// A frame had the level to fail on, or a more severe one
+    FailedOnLevel,
+    /// The timeout passed
+    Timeout,
+    /// The maximum number of frames was decoded
+    MaxFrames,
+    /// The input ended without the message of any frame matching the pattern
+    NoMatch,
+}
+
+impl ExitReason {
+    /// Returns the exit code of the process, which is distinct for each reason, and from `1` for
+    /// errors.
+    pub fn code(self) -> i32 {
+        match self {
+            ExitReason::Matched => 0,
+            ExitReason::FailedOnLevel => 2,
+            ExitReason::Timeout => 3,
+            ExitReason::MaxFrames => 4,
+            ExitReason::NoMatch => 5,
+        }
+    }
+}
+
+/// Conditions to stop decoding on, e.g. to end a hardware-in-the-loop test once the firmware logs
+/// that it passed, or failed.
+///
+/// The conditions are checked in the order of the [`ExitReason`]s after every frame, see
+/// [`ExitConditions::check`], and once more when the input ends, see [`ExitConditions::end`].
+#[derive(Debug, Default)]
+pub struct ExitConditions {
+    exit_on_match: Option<Regex>,
+    fail_on_level: Option<Level>,
+    deadline: Option<Instant>,
+    max_frames: Option<usize>,
+    frames: usize,
+}
+
+impl ExitConditions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stops once the message of a frame matches `pattern`.
+    pub fn exit_on_match(mut self, pattern: Regex) -> Self {
+        self.exit_on_match = Some(pattern);
+        self
+    }
+
+    /// Stops once a frame has `level`, or a more severe one.
+    pub fn fail_on_level(mut self, level: Level) -> Self {
+        self.fail_on_level = Some(level);
+        self
+    }
+
+    /// Stops once `timeout` has passed since this call.
+    ///
+    /// This is only checked after every frame, see [`ExitConditions::check_deadline`]; to also
+    /// stop while waiting for data, read it with [`read_with_deadline`].
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.deadline = Some(Instant::now() + timeout);
+        self
+    }
+
+    /// Stops after `max_frames` frames.
+    pub fn max_frames(mut self, max_frames: usize) -> Self {
+        self.max_frames = Some(max_frames);
+        self
+    }
+
+    /// Returns when the timeout passes, if there is one.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Checks the conditions after `frame` was decoded, and returns why to stop, if any.
+    pub fn check(&mut self, frame: &Frame<'_>) -> Option<ExitReason> {
+        self.frames += 1;
+
+        if let Some(pattern) = &self.exit_on_match {
+            if pattern.is_match(&frame.display_message().to_string()) {
+                return Some(ExitReason::Matched);
+            }
+        }
+        if let (Some(fail_on_level), Some(level)) = (self.fail_on_level, frame.level()) {
+            if level >= fail_on_level {
+                return Some(ExitReason::FailedOnLevel);
+            }
+        }
+        if let Some(reason) = self.check_deadline() {
+            return Some(reason);
+        }
+        if self.max_frames.is_some_and(|max| self.frames >= max) {
+            return Some(ExitReason::MaxFrames);
+        }
+        None
+    }
+
+    /// Checks only the timeout, e.g. after a frame that could not be decoded, and returns
+    /// [`ExitReason::Timeout`] if it has passed.
+    pub fn check_deadline(&self) -> Option<ExitReason> {
+        self.deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+            .then_some(ExitReason::Timeout)
+    }
+
+    /// Checks the conditions once the input ended, without any of them being met, and returns
+    /// [`ExitReason::NoMatch`] if there was a pattern to match.
+    pub fn end(&self) -> Option<ExitReason> {
+        self.exit_on_match.as_ref().map(|_| ExitReason::NoMatch)
+    }
+}
+
+/// Parses a log level like `warn`, e.g. for [`ExitConditions::fail_on_level`].
+pub fn parse_level(s: &str) -> Result<Level, anyhow::Error> {
+    match s {
+        "trace" => Ok(Level::Trace),
+        "debug" => Ok(Level::Debug),
+        "info" => Ok(Level::Info),
+        "warn" => Ok(Level::Warn),
+        "error" => Ok(Level::Error),
+        _ => Err(anyhow!(
+            "unknown log level `{s}`, expected trace, debug, info, warn or error"
+        )),
+    }
+}
+
+/// Reads from `reader` on another thread, so that reading can fail with
+/// [`io::ErrorKind::TimedOut`] once `deadline` passes, instead of waiting for data forever.
+pub fn read_with_deadline<R: io::Read + Send + 'static>(
+    reader: R,
+    deadline: Instant,
+) -> impl io::Read {
+    let (tx, rx) = mpsc::sync_channel(16);
+    thread::spawn(move || {
+        let mut reader = reader;
+        let mut buf = [0; 1024];
+        loop {
+            let data = match reader.read(&mut buf) {
+                Ok(0) => return,
+                Ok(n) => Ok(buf[..n].to_vec()),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => Err(e),
+            };
+            // stop once the `DeadlineReader` is dropped
+            if tx.send(data).is_err() {
+                return;
+            }
+        }
+    });
+
+    DeadlineReader {
+        rx,
+        deadline,
+        data: Vec::new(),
+        pos: 0,
+    }
+}
+
+struct DeadlineReader {
+    rx: Receiver<io::Result<Vec<u8>>>,
+    deadline: Instant,
+    /// Data that was received but not read yet, from `pos` on
+    data: Vec<u8>,
+    pos: usize,
+}
+
+impl io::Read for DeadlineReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos == self.data.len() {
+            let timeout = self.deadline.saturating_duration_since(Instant::now());
+            self.data = match self.rx.recv_timeout(timeout) {
+                Ok(data) => data?,
+                Err(RecvTimeoutError::Timeout) => return Err(io::ErrorKind::TimedOut.into()),
+                // the reader reached its end
+                Err(RecvTimeoutError::Disconnected) => return Ok(0),
+            };
+            self.pos = 0;
+        }
+
+        let n = buf.len().min(self.data.len() - self.pos);
+        buf[..n].copy_from_slice(&self.data[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read as _;
+
+    use super::*;
+    use crate::{Encoding, Table, Tag};
+
+    #[test]
+    fn check() {
+        let table = Table::with_entries(
+            &[(0, Tag::Info, "x={=u8}"), (1, Tag::Error, "failed")],
+            Encoding::Raw,
+        );
+        let (info, _) = table.decode(&[0, 0, 7]).unwrap();
+        let (error, _) = table.decode(&[1, 0]).unwrap();
+
+        let mut conditions = ExitConditions::new().exit_on_match(Regex::new("x=[0-9]+").unwrap());
+        assert_eq!(conditions.check(&error), None);
+        assert_eq!(conditions.end(), Some(ExitReason::NoMatch));
+        assert_eq!(conditions.check(&info), Some(ExitReason::Matched));
+        assert_eq!(ExitConditions::new().end(), None);
+
+        let mut conditions = ExitConditions::new().fail_on_level(parse_level("warn").unwrap());
+        assert_eq!(conditions.check(&info), None);
+        assert_eq!(conditions.check(&error), Some(ExitReason::FailedOnLevel));
+
+        let mut conditions = ExitConditions::new().max_frames(2);
+        assert_eq!(conditions.check(&info), None);
+        assert_eq!(conditions.check(&info), Some(ExitReason::MaxFrames));
+
+        let mut conditions = ExitConditions::new().timeout(Duration::ZERO);
+        assert_eq!(conditions.check_deadline(), Some(ExitReason::Timeout));
+        assert_eq!(conditions.check(&info), Some(ExitReason::Timeout));
+    }
+
+    #[test]
+    fn deadline() {
+        let mut reader = read_with_deadline(&b"data"[..], Instant::now() + Duration::from_secs(10));
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data).unwrap();
+        assert_eq!(data, b"data");
+
+        /// Never has data
+        struct Silent;
+
+        impl io::Read for Silent {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                thread::sleep(Duration::from_secs(1));
+                Ok(0)
+            }
+        }
+
+        let mut reader = read_with_deadline(Silent, Instant::now() + Duration::from_millis(50));
+        assert_eq!(
+            reader.read(&mut [0; 4]).unwrap_err().kind(),
+            io::ErrorKind::TimedOut
+        );
+    }
+}
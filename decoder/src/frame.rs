@@ -2,11 +2,15 @@ use std::{
     convert::TryFrom,
     fmt::{self, Write as _},
     mem,
+    net::{Ipv4Addr, Ipv6Addr},
+    ops::Range,
 };
 
-use crate::{Arg, BitflagsKey, Table};
+use crate::{Arg, BitflagsKey, HintFormatters, HintValue, Table};
 use colored::Colorize;
-use defmt_parser::{DisplayHint, Fragment, Level, ParserMode, TimePrecision, Type};
+use defmt_parser::{
+    Alignment, DisplayHint, Fragment, Level, Parameter, ParserMode, TimePrecision, Type,
+};
 use time::{macros::format_description, OffsetDateTime};
 
 /// Used to convert a `i128` value into right target type in hex
@@ -78,17 +82,23 @@ impl<'t> Frame<'t> {
         DisplayFrame {
             frame: self,
             colored,
+            hint_formatters: None,
         }
     }
 
     pub fn display_timestamp(&'t self) -> Option<DisplayTimestamp<'t>> {
-        self.timestamp_format
-            .map(|_| DisplayTimestamp { frame: self })
+        self.timestamp_format.map(|_| DisplayTimestamp {
+            frame: self,
+            hint_formatters: None,
+        })
     }
 
     /// Returns a struct that will format the message contained in this log frame.
     pub fn display_message(&'t self) -> DisplayMessage<'t> {
-        DisplayMessage { frame: self }
+        DisplayMessage {
+            frame: self,
+            hint_formatters: None,
+        }
     }
 
     pub fn level(&self) -> Option<Level> {
@@ -99,8 +109,27 @@ impl<'t> Frame<'t> {
         self.index
     }
 
-    fn format_args(&self, format: &str, args: &[Arg], parent_hint: Option<&DisplayHint>) -> String {
-        self.format_args_real(format, args, parent_hint).unwrap() // cannot fail, we only write to a `String`
+    /// Returns the raw value of the timestamp, if any.
+    ///
+    /// This is the first integer argument of the timestamp format string. Its unit depends on the
+    /// firmware, e.g. with `{=u64:us}` it is microseconds.
+    pub fn timestamp(&self) -> Option<u128> {
+        self.timestamp_args.iter().find_map(|arg| match arg {
+            Arg::Uxx(x) => Some(*x),
+            Arg::Ixx(x) => u128::try_from(*x).ok(),
+            _ => None,
+        })
+    }
+
+    fn format_args(
+        &self,
+        format: &str,
+        args: &[Arg],
+        parent_hint: Option<&DisplayHint>,
+        formatters: Option<&HintFormatters>,
+    ) -> String {
+        self.format_args_real(format, args, parent_hint, formatters)
+            .unwrap() // cannot fail, we only write to a `String`
     }
 
     fn format_args_real(
@@ -108,6 +137,7 @@ impl<'t> Frame<'t> {
         format: &str,
         args: &[Arg],
         parent_hint: Option<&DisplayHint>,
+        formatters: Option<&HintFormatters>,
     ) -> Result<String, fmt::Error> {
         let params = defmt_parser::parse(format, ParserMode::ForwardsCompatible).unwrap();
         let mut buf = String::new();
@@ -118,101 +148,175 @@ impl<'t> Frame<'t> {
                 }
                 Fragment::Parameter(param) => {
                     let hint = param.hint.as_ref().or(parent_hint);
+                    let arg = &args[param.index];
 
-                    match &args[param.index] {
-                        Arg::Bool(x) => write!(buf, "{x}")?,
-                        Arg::F32(x) => write!(buf, "{}", ryu::Buffer::new().format(*x))?,
-                        Arg::F64(x) => write!(buf, "{}", ryu::Buffer::new().format(*x))?,
-                        Arg::Uxx(x) => {
-                            match param.ty {
-                                Type::BitField(range) => {
-                                    let left_zeroes =
-                                        mem::size_of::<u128>() * 8 - range.end as usize;
-                                    let right_zeroes = left_zeroes + range.start as usize;
-                                    // isolate the desired bitfields
-                                    let bitfields = (*x << left_zeroes) >> right_zeroes;
-
-                                    if let Some(DisplayHint::Ascii) = hint {
-                                        let bstr = bitfields
-                                            .to_be_bytes()
-                                            .iter()
-                                            .skip(right_zeroes / 8)
-                                            .copied()
-                                            .collect::<Vec<u8>>();
-                                        self.format_bytes(&bstr, hint, &mut buf)?
-                                    } else {
-                                        self.format_u128(bitfields, hint, &mut buf)?;
-                                    }
-                                }
-                                _ => match hint {
-                                    Some(DisplayHint::ISO8601(precision)) => {
-                                        self.format_iso8601(*x as u64, precision, &mut buf)?
-                                    }
-                                    Some(DisplayHint::Debug) => {
-                                        self.format_u128(*x, parent_hint, &mut buf)?
-                                    }
-                                    _ => self.format_u128(*x, hint, &mut buf)?,
-                                },
-                            }
+                    match hint {
+                        Some(&DisplayHint::NoHint {
+                            zero_pad,
+                            sign,
+                            align,
+                            width,
+                            precision,
+                        }) if width > 0 => {
+                            // pad the whole value, but not the fields nested inside of it
+                            let hint = DisplayHint::NoHint {
+                                zero_pad,
+                                sign,
+                                align: None,
+                                width: 0,
+                                precision,
+                            };
+                            let mut value = String::new();
+                            self.format_arg(
+                                arg,
+                                &param,
+                                Some(&hint),
+                                parent_hint,
+                                formatters,
+                                &mut value,
+                            )?;
+
+                            let is_number = matches!(
+                                arg,
+                                Arg::Uxx(_) | Arg::Ixx(_) | Arg::F32(_) | Arg::F64(_)
+                            );
+                            let align = align.unwrap_or(match is_number {
+                                true => Alignment::Right,
+                                false => Alignment::Left,
+                            });
+                            let padding = width.saturating_sub(value.chars().count());
+                            let (left, right) = match align {
+                                Alignment::Left => (0, padding),
+                                Alignment::Center => (padding / 2, padding - padding / 2),
+                                Alignment::Right => (padding, 0),
+                            };
+                            write!(buf, "{:left$}{value}{:right$}", "", "")?;
                         }
-                        Arg::Ixx(x) => self.format_i128(*x, param.ty, hint, &mut buf)?,
-                        Arg::Str(x) | Arg::Preformatted(x) => self.format_str(x, hint, &mut buf)?,
-                        Arg::IStr(x) => self.format_str(x, hint, &mut buf)?,
-                        Arg::Format { format, args } => match parent_hint {
-                            Some(DisplayHint::Ascii) => {
-                                buf.push_str(&self.format_args(format, args, parent_hint));
-                            }
-                            _ => buf.push_str(&self.format_args(format, args, hint)),
-                        },
-                        Arg::FormatSequence { args } => {
-                            for arg in args {
-                                buf.push_str(&self.format_args("{=?}", &[arg.clone()], hint))
-                            }
+                        _ => {
+                            self.format_arg(arg, &param, hint, parent_hint, formatters, &mut buf)?
                         }
-                        Arg::FormatSlice { elements } => {
-                            match hint {
-                                // Filter Ascii Hints, which contains u8 byte slices
-                                Some(DisplayHint::Ascii)
-                                    if elements.iter().filter(|e| e.format == "{=u8}").count()
-                                        != 0 =>
-                                {
-                                    let vals = elements
-                                        .iter()
-                                        .map(|e| match e.args.as_slice() {
-                                            [Arg::Uxx(v)] => u8::try_from(*v)
-                                                .expect("the value must be in u8 range"),
-                                            _ => panic!(
-                                                "FormatSlice should only contain one argument"
-                                            ),
-                                        })
-                                        .collect::<Vec<u8>>();
-                                    self.format_bytes(&vals, hint, &mut buf)?
-                                }
-                                _ => {
-                                    buf.write_str("[")?;
-                                    let mut is_first = true;
-                                    for element in elements {
-                                        if !is_first {
-                                            buf.write_str(", ")?;
-                                        }
-                                        is_first = false;
-                                        buf.write_str(&self.format_args(
-                                            element.format,
-                                            &element.args,
-                                            hint,
-                                        ))?;
-                                    }
-                                    buf.write_str("]")?;
+                    }
+                }
+            }
+        }
+        Ok(buf)
+    }
+
+    fn format_arg(
+        &self,
+        arg: &Arg,
+        param: &Parameter,
+        hint: Option<&DisplayHint>,
+        parent_hint: Option<&DisplayHint>,
+        formatters: Option<&HintFormatters>,
+        buf: &mut String,
+    ) -> Result<(), fmt::Error> {
+        if let (Some(DisplayHint::Custom(name)), Some(formatters)) = (hint, formatters) {
+            if let Some(formatter) = formatters.get(name) {
+                let value = match (arg, &param.ty) {
+                    (Arg::Uxx(x), Type::BitField(range)) => {
+                        Some(HintValue::Uxx(isolate_bitfield(*x, range)))
+                    }
+                    _ => HintValue::from_arg(arg),
+                };
+                if let Some(value) = value {
+                    return formatter(value, buf);
+                }
+            }
+        }
+
+        match arg {
+            Arg::Bool(x) => write!(buf, "{x}")?,
+            Arg::F32(x) => {
+                self.format_float(*x as f64, ryu::Buffer::new().format(*x), hint, buf)?
+            }
+            Arg::F64(x) => self.format_float(*x, ryu::Buffer::new().format(*x), hint, buf)?,
+            Arg::Uxx(x) => match &param.ty {
+                Type::BitField(range) => {
+                    let left_zeroes = mem::size_of::<u128>() * 8 - range.end as usize;
+                    let right_zeroes = left_zeroes + range.start as usize;
+                    let bitfields = isolate_bitfield(*x, range);
+
+                    if let Some(DisplayHint::Ascii) = hint {
+                        let bstr = bitfields
+                            .to_be_bytes()
+                            .iter()
+                            .skip(right_zeroes / 8)
+                            .copied()
+                            .collect::<Vec<u8>>();
+                        self.format_bytes(&bstr, hint, buf)?
+                    } else {
+                        self.format_u128(bitfields, hint, buf)?;
+                    }
+                }
+                _ => match hint {
+                    Some(DisplayHint::ISO8601(precision)) => {
+                        self.format_iso8601(*x as u64, precision, buf)?
+                    }
+                    Some(DisplayHint::Ipv4) => write!(buf, "{}", Ipv4Addr::from(*x as u32))?,
+                    Some(DisplayHint::Debug) => self.format_u128(*x, parent_hint, buf)?,
+                    _ => self.format_u128(*x, hint, buf)?,
+                },
+            },
+            Arg::Ixx(x) => self.format_i128(*x, param.ty.clone(), hint, buf)?,
+            Arg::Str(x) | Arg::Preformatted(x) => self.format_str(x, hint, buf)?,
+            Arg::IStr(x) => self.format_str(x, hint, buf)?,
+            Arg::Format { format, args } => match parent_hint {
+                Some(DisplayHint::Ascii) => {
+                    buf.push_str(&self.format_args(format, args, parent_hint, formatters));
+                }
+                _ => buf.push_str(&self.format_args(format, args, hint, formatters)),
+            },
+            Arg::FormatSequence { args } => {
+                for arg in args {
+                    buf.push_str(&self.format_args("{=?}", &[arg.clone()], hint, formatters))
+                }
+            }
+            Arg::FormatSlice { elements } => {
+                match hint {
+                    // Filter Ascii and other byte Hints, which contains u8 byte slices
+                    Some(
+                        DisplayHint::Ascii
+                        | DisplayHint::Ipv4
+                        | DisplayHint::Ipv6
+                        | DisplayHint::Mac
+                        | DisplayHint::Uuid
+                        | DisplayHint::Hexdump,
+                    ) if elements.iter().filter(|e| e.format == "{=u8}").count() != 0 => {
+                        let vals = elements
+                            .iter()
+                            .map(|e| match e.args.as_slice() {
+                                [Arg::Uxx(v)] => {
+                                    u8::try_from(*v).expect("the value must be in u8 range")
                                 }
+                                _ => panic!("FormatSlice should only contain one argument"),
+                            })
+                            .collect::<Vec<u8>>();
+                        self.format_bytes(&vals, hint, buf)?
+                    }
+                    _ => {
+                        buf.write_str("[")?;
+                        let mut is_first = true;
+                        for element in elements {
+                            if !is_first {
+                                buf.write_str(", ")?;
                             }
+                            is_first = false;
+                            buf.write_str(&self.format_args(
+                                element.format,
+                                &element.args,
+                                hint,
+                                formatters,
+                            ))?;
                         }
-                        Arg::Slice(x) => self.format_bytes(x, hint, &mut buf)?,
-                        Arg::Char(c) => write!(buf, "{c}")?,
+                        buf.write_str("]")?;
                     }
                 }
             }
+            Arg::Slice(x) => self.format_bytes(x, hint, buf)?,
+            Arg::Char(c) => write!(buf, "{c}")?,
         }
-        Ok(buf)
+        Ok(())
     }
 
     fn format_u128(
@@ -222,7 +326,10 @@ impl<'t> Frame<'t> {
         buf: &mut String,
     ) -> Result<(), fmt::Error> {
         match hint {
-            Some(DisplayHint::NoHint { zero_pad }) => write!(buf, "{x:0zero_pad$}")?,
+            Some(DisplayHint::NoHint { zero_pad, sign, .. }) => match sign {
+                true => write!(buf, "{x:+0zero_pad$}")?,
+                false => write!(buf, "{x:0zero_pad$}")?,
+            },
             Some(DisplayHint::Binary {
                 alternate,
                 zero_pad,
@@ -295,7 +402,10 @@ impl<'t> Frame<'t> {
         buf: &mut String,
     ) -> Result<(), fmt::Error> {
         match hint {
-            Some(DisplayHint::NoHint { zero_pad }) => write!(buf, "{x:0zero_pad$}")?,
+            Some(DisplayHint::NoHint { zero_pad, sign, .. }) => match sign {
+                true => write!(buf, "{x:+0zero_pad$}")?,
+                false => write!(buf, "{x:0zero_pad$}")?,
+            },
             Some(DisplayHint::Binary {
                 alternate,
                 zero_pad,
@@ -367,21 +477,109 @@ impl<'t> Frame<'t> {
                 }
                 buf.push(']');
             }
+            Some(DisplayHint::Ipv4) if bytes.len() == 4 => {
+                let octets: [u8; 4] = bytes.try_into().unwrap();
+                write!(buf, "{}", Ipv4Addr::from(octets))?
+            }
+            Some(DisplayHint::Ipv6) if bytes.len() == 16 => {
+                let octets: [u8; 16] = bytes.try_into().unwrap();
+                write!(buf, "{}", Ipv6Addr::from(octets))?
+            }
+            Some(DisplayHint::Mac) if bytes.len() == 6 => {
+                let mut is_first = true;
+                for byte in bytes {
+                    if !is_first {
+                        buf.push(':');
+                    }
+                    is_first = false;
+                    write!(buf, "{byte:02x}")?;
+                }
+            }
+            Some(DisplayHint::Uuid) if bytes.len() == 16 => {
+                for (i, byte) in bytes.iter().enumerate() {
+                    if matches!(i, 4 | 6 | 8 | 10) {
+                        buf.push('-');
+                    }
+                    write!(buf, "{byte:02x}")?;
+                }
+            }
+            Some(DisplayHint::Hexdump) => self.format_hexdump(bytes, buf)?,
+            // also used if the length does not match the hint
             _ => write!(buf, "{bytes:?}")?,
         }
         Ok(())
     }
 
+    /// Formats `bytes` like `xxd -g 1` does: one line per 16 bytes, each line consisting of the
+    /// offset, the bytes in hex and the bytes as ASCII, with `.` for non-printable ones.
+    fn format_hexdump(&self, bytes: &[u8], buf: &mut String) -> Result<(), fmt::Error> {
+        const BYTES_PER_LINE: usize = 16;
+
+        for (i, line) in bytes.chunks(BYTES_PER_LINE).enumerate() {
+            if i != 0 {
+                buf.push('\n');
+            }
+            write!(buf, "{:08x}:", i * BYTES_PER_LINE)?;
+            for byte in line {
+                write!(buf, " {byte:02x}")?;
+            }
+            // align the ASCII column of the last line
+            for _ in line.len()..BYTES_PER_LINE {
+                buf.push_str("   ");
+            }
+            buf.push_str("  ");
+            for byte in line {
+                match byte.is_ascii_graphic() || *byte == b' ' {
+                    true => buf.push(*byte as char),
+                    false => buf.push('.'),
+                }
+            }
+        }
+        Ok(())
+    }
+
     fn format_str(
         &self,
         s: &str,
         hint: Option<&DisplayHint>,
         buf: &mut String,
     ) -> Result<(), fmt::Error> {
-        if hint == Some(&DisplayHint::Debug) {
-            write!(buf, "{s:?}")?;
-        } else {
-            buf.push_str(s);
+        match hint {
+            Some(DisplayHint::Debug) => write!(buf, "{s:?}")?,
+            Some(DisplayHint::NoHint {
+                precision: Some(precision),
+                ..
+            }) => write!(buf, "{s:.precision$}")?,
+            _ => buf.push_str(s),
+        }
+        Ok(())
+    }
+
+    /// Formats a float, using the shortest representation that roundtrips (`shortest`) unless
+    /// the hint sets a precision.
+    fn format_float(
+        &self,
+        x: f64,
+        shortest: &str,
+        hint: Option<&DisplayHint>,
+        buf: &mut String,
+    ) -> Result<(), fmt::Error> {
+        let (sign, precision) = match hint {
+            Some(DisplayHint::NoHint {
+                sign, precision, ..
+            }) => (*sign, *precision),
+            _ => (false, None),
+        };
+        match (sign, precision) {
+            (false, None) => buf.push_str(shortest),
+            (true, None) => {
+                if x.is_sign_positive() && !x.is_nan() {
+                    buf.push('+');
+                }
+                buf.push_str(shortest);
+            }
+            (false, Some(precision)) => write!(buf, "{x:.precision$}")?,
+            (true, Some(precision)) => write!(buf, "{x:+.precision$}")?,
         }
         Ok(())
     }
@@ -409,8 +607,24 @@ impl<'t> Frame<'t> {
     }
 }
 
+/// Isolates the bits `range` of `x`.
+fn isolate_bitfield(x: u128, range: &Range<u8>) -> u128 {
+    let left_zeroes = mem::size_of::<u128>() * 8 - range.end as usize;
+    let right_zeroes = left_zeroes + range.start as usize;
+    (x << left_zeroes) >> right_zeroes
+}
+
 pub struct DisplayTimestamp<'t> {
     frame: &'t Frame<'t>,
+    hint_formatters: Option<&'t HintFormatters>,
+}
+
+impl<'t> DisplayTimestamp<'t> {
+    /// Formats parameters with custom display hints (`{:@name}`) using `formatters`.
+    pub fn with_hint_formatters(mut self, formatters: &'t HintFormatters) -> Self {
+        self.hint_formatters = Some(formatters);
+        self
+    }
 }
 
 impl fmt::Display for DisplayTimestamp<'_> {
@@ -419,6 +633,7 @@ impl fmt::Display for DisplayTimestamp<'_> {
             self.frame.timestamp_format.unwrap(),
             &self.frame.timestamp_args,
             None,
+            self.hint_formatters,
         );
         f.write_str(&args)
     }
@@ -426,13 +641,25 @@ impl fmt::Display for DisplayTimestamp<'_> {
 
 pub struct DisplayMessage<'t> {
     frame: &'t Frame<'t>,
+    hint_formatters: Option<&'t HintFormatters>,
+}
+
+impl<'t> DisplayMessage<'t> {
+    /// Formats parameters with custom display hints (`{:@name}`) using `formatters`.
+    pub fn with_hint_formatters(mut self, formatters: &'t HintFormatters) -> Self {
+        self.hint_formatters = Some(formatters);
+        self
+    }
 }
 
 impl fmt::Display for DisplayMessage<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let args = self
-            .frame
-            .format_args(self.frame.format, &self.frame.args, None);
+        let args = self.frame.format_args(
+            self.frame.format,
+            &self.frame.args,
+            None,
+            self.hint_formatters,
+        );
         f.write_str(&args)
     }
 }
@@ -442,6 +669,15 @@ impl fmt::Display for DisplayMessage<'_> {
 pub struct DisplayFrame<'t> {
     frame: &'t Frame<'t>,
     colored: bool,
+    hint_formatters: Option<&'t HintFormatters>,
+}
+
+impl<'t> DisplayFrame<'t> {
+    /// Formats parameters with custom display hints (`{:@name}`) using `formatters`.
+    pub fn with_hint_formatters(mut self, formatters: &'t HintFormatters) -> Self {
+        self.hint_formatters = Some(formatters);
+        self
+    }
 }
 
 impl fmt::Display for DisplayFrame<'_> {
@@ -475,15 +711,22 @@ impl fmt::Display for DisplayFrame<'_> {
             .map(|fmt| {
                 format!(
                     "{} ",
-                    self.frame
-                        .format_args(fmt, &self.frame.timestamp_args, None,),
+                    self.frame.format_args(
+                        fmt,
+                        &self.frame.timestamp_args,
+                        None,
+                        self.hint_formatters
+                    ),
                 )
             })
             .unwrap_or_default();
 
-        let args = self
-            .frame
-            .format_args(self.frame.format, &self.frame.args, None);
+        let args = self.frame.format_args(
+            self.frame.format,
+            &self.frame.args,
+            None,
+            self.hint_formatters,
+        );
 
         write!(f, "{timestamp}{level}{args}")
     }
@@ -0,0 +1,429 @@
+//! Decoding frames that don't borrow the decoder.
+
+use std::{fmt, io, sync::Arc};
+
+use anyhow::bail;
+#[cfg(feature = "async")]
+use std::{
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use crate::{
+    stream::{self, Unframe},
+    DecodeError, Frame, Table,
+};
+
+const READ_BUFFER_SIZE: usize = 1024;
+
+/// A log frame that owns its data, so that it can be stored or sent to another thread.
+///
+/// It keeps the data of the frame and the [`Table`] it was decoded with, and decodes the data
+/// again when [`frame`](OwnedFrame::frame) is called. The data was validated when the frame was
+/// decoded the first time, so decoding it again can't fail.
+#[derive(Clone)]
+pub struct OwnedFrame {
+    table: Arc<Table>,
+    data: Vec<u8>,
+}
+
+impl OwnedFrame {
+    pub fn frame(&self) -> Frame<'_> {
+        let (frame, _consumed) = self
+            .table
+            .decode(&self.data)
+            .expect("the data was decoded before");
+        frame
+    }
+
+    pub fn table(&self) -> &Arc<Table> {
+        &self.table
+    }
+}
+
+impl fmt::Debug for OwnedFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.frame(), f)
+    }
+}
+
+/// What the [`Unframe`] has to offer next.
+enum Next {
+    Frame(Result<OwnedFrame, DecodeError>),
+    /// More data needs to be read
+    Read,
+    End,
+}
+
+/// The state shared by [`Frames`] and [`AsyncFrames`].
+struct State {
+    /// The tables of the images that may write the frames
+    tables: Vec<Arc<Table>>,
+    /// Index in `tables` of the image that wrote the last frame
+    current: usize,
+    unframe: Box<dyn Unframe>,
+    buf: Box<[u8]>,
+    eof: bool,
+    /// A frame couldn't be decoded, and the encoding can't recover from that
+    failed: bool,
+}
+
+impl State {
+    fn new(table: Arc<Table>) -> Self {
+        Self::from_images(vec![table]).expect("a single image is always valid")
+    }
+
+    fn from_images(tables: Vec<Arc<Table>>) -> Result<Self, anyhow::Error> {
+        let Some(first) = tables.first() else {
+            bail!("no image to decode the frames with");
+        };
+        if tables.iter().any(|table| {
+            table.encoding() != first.encoding()
+                || table.integer_encoding() != first.integer_encoding()
+        }) {
+            bail!("all images must use the same defmt encoding");
+        }
+
+        Ok(Self {
+            unframe: stream::unframe(first.encoding()),
+            tables,
+            current: 0,
+            buf: vec![0; READ_BUFFER_SIZE].into_boxed_slice(),
+            eof: false,
+            failed: false,
+        })
+    }
+
+    fn next(&mut self) -> Next {
+        if self.failed {
+            return Next::End;
+        }
+        let next = self.next_frame();
+        if let Next::Frame(Err(_)) = next {
+            // without framing, it's unknown where the next frame starts
+            self.failed = !self.tables[self.current].encoding().can_recover();
+        }
+        next
+    }
+
+    fn next_frame(&mut self) -> Next {
+        loop {
+            let table = &self.tables[self.current];
+            let data = match self.unframe.next_frame(table) {
+                Ok(data) => data,
+                Err(DecodeError::UnexpectedEof) if self.eof => return Next::End,
+                Err(DecodeError::UnexpectedEof) => return Next::Read,
+                Err(e) => return Next::Frame(Err(e)),
+            };
+
+            let id = match stream::image_id(table, data) {
+                Ok(Some((id, _len))) => id,
+                // validate the frame, so that `OwnedFrame::frame` can't fail
+                _ => {
+                    return Next::Frame(match table.decode(data) {
+                        Ok(_) => Ok(OwnedFrame {
+                            table: table.clone(),
+                            data: data.to_vec(),
+                        }),
+                        // the frame is complete, so missing data means it is corrupted
+                        Err(DecodeError::UnexpectedEof) => Err(DecodeError::Malformed),
+                        Err(e) => Err(e),
+                    });
+                }
+            };
+            // the following frames are written by the image with this ID
+            match self.tables.iter().position(|t| t.image_id() == Some(id)) {
+                Some(current) => self.current = current,
+                // with a single image, there is nothing to choose from
+                None if self.tables.len() == 1 => {}
+                None => return Next::Frame(Err(DecodeError::Malformed)),
+            }
+        }
+    }
+
+    /// Passes the first `n` bytes of `buf` on, after they have been read into it.
+    fn read(&mut self, n: usize) {
+        if n == 0 {
+            self.eof = true;
+        } else {
+            self.unframe.received(&self.buf[..n]);
+        }
+    }
+}
+
+/// An iterator over the frames read from an [`io::Read`]er, returned by [`Table::frames`].
+///
+/// Yields an `Err` if reading fails, and an `Ok(Err(_))` for frames that can't be decoded. Whether
+/// the following frames can still be decoded depends on the encoding, see
+/// [`Encoding::can_recover`](crate::Encoding::can_recover). Iteration ends when the reader reaches
+/// the end of its data; an incomplete frame at the end is ignored.
+pub struct Frames<R> {
+    state: State,
+    reader: R,
+}
+
+impl<R> Frames<R> {
+    pub(crate) fn new(table: Arc<Table>, reader: R) -> Self {
+        Self {
+            state: State::new(table),
+            reader,
+        }
+    }
+
+    /// Decodes the frames read from `reader`, which several images of the firmware write, like a
+    /// bootloader and an application.
+    ///
+    /// The images identify themselves with a frame before their first log frame, if they are built
+    /// with the `image-id` feature of `defmt`, and the following frames are decoded with the table
+    /// whose [`image_id`](Table::image_id) matches. Frames are decoded with the first table until an
+    /// image identifies itself, and an unknown image yields a `DecodeError::Malformed`.
+    ///
+    /// Fails if `tables` is empty, or if the tables use different encodings.
+    pub fn from_images(tables: Vec<Arc<Table>>, reader: R) -> Result<Self, anyhow::Error> {
+        Ok(Self {
+            state: State::from_images(tables)?,
+            reader,
+        })
+    }
+}
+
+impl<R: io::Read> Iterator for Frames<R> {
+    type Item = io::Result<Result<OwnedFrame, DecodeError>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.state.next() {
+                Next::Frame(frame) => return Some(Ok(frame)),
+                Next::Read => {}
+                Next::End => return None,
+            }
+
+            match self.reader.read(&mut self.state.buf) {
+                Ok(n) => self.state.read(n),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+/// A [`Stream`](futures_core::Stream) of the frames read from a tokio
+/// [`AsyncRead`](tokio::io::AsyncRead)er, returned by [`Table::frames_async`].
+///
+/// Yields the same items as [`Frames`].
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+pub struct AsyncFrames<R> {
+    state: State,
+    reader: R,
+}
+
+#[cfg(feature = "async")]
+impl<R> AsyncFrames<R> {
+    pub(crate) fn new(table: Arc<Table>, reader: R) -> Self {
+        Self {
+            state: State::new(table),
+            reader,
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl<R: tokio::io::AsyncRead + Unpin> futures_core::Stream for AsyncFrames<R> {
+    type Item = io::Result<Result<OwnedFrame, DecodeError>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            match this.state.next() {
+                Next::Frame(frame) => return Poll::Ready(Some(Ok(frame))),
+                Next::Read => {}
+                Next::End => return Poll::Ready(None),
+            }
+
+            let mut buf = tokio::io::ReadBuf::new(&mut this.state.buf);
+            match ready!(Pin::new(&mut this.reader).poll_read(cx, &mut buf)) {
+                Ok(()) => {
+                    let n = buf.filled().len();
+                    this.state.read(n)
+                }
+                Err(e) => return Poll::Ready(Some(Err(e))),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Encoding, Tag};
+
+    fn table() -> Arc<Table> {
+        Arc::new(Table::with_entries(
+            &[(1, Tag::Info, "x={=u8}")],
+            Encoding::Rzcobs,
+        ))
+    }
+
+    fn message(frame: Result<OwnedFrame, DecodeError>) -> Result<String, DecodeError> {
+        frame.map(|frame| frame.frame().display_message().to_string())
+    }
+
+    #[test]
+    fn frames() {
+        // 2 frames, a malformed one, and an incomplete one
+        let data: &[u8] = &[0, 1, 42, 0x7a, 0, 1, 43, 0x7a, 0, 0x80, 0, 1, 44];
+        let mut frames = table().frames(data);
+
+        let first = frames.next().unwrap().unwrap();
+        let second = frames.next().unwrap().unwrap();
+        assert_eq!(message(first).unwrap(), "x=42");
+        assert_eq!(message(second).unwrap(), "x=43");
+        assert_eq!(
+            message(frames.next().unwrap().unwrap()),
+            Err(DecodeError::Malformed)
+        );
+        assert!(frames.next().is_none());
+    }
+
+    #[test]
+    fn undecodable_frames() {
+        // a frame with an unknown index, between 2 good ones
+        let data: &[u8] = &[0, 1, 42, 0x7a, 0, 9, 42, 0x7a, 0, 1, 43, 0x7a, 0];
+        let frames = table()
+            .frames(data)
+            .map(|frame| message(frame.unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            frames,
+            [
+                Ok("x=42".to_owned()),
+                Err(DecodeError::Malformed),
+                Ok("x=43".to_owned()),
+            ]
+        );
+
+        // without framing, decoding stops at the first frame that can't be decoded
+        let table = Arc::new(Table::with_entries(
+            &[(1, Tag::Info, "x={=u8}")],
+            Encoding::Raw,
+        ));
+        let data: &[u8] = &[1, 0, 42, 9, 0, 43, 1, 0, 44];
+        let frames = table
+            .frames(data)
+            .map(|frame| message(frame.unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(frames, [Ok("x=42".to_owned()), Err(DecodeError::Malformed)]);
+    }
+
+    #[test]
+    fn images() {
+        let table = |string: &str, image_id| {
+            let mut table = Table::with_entries(&[(1, Tag::Info, string)], Encoding::Raw);
+            table.image_id = Some(image_id);
+            Arc::new(table)
+        };
+        let bootloader = table("bootloader x={=u8}", 0x0800_0101);
+        let app = table("app x={=u8}", 0x0801_0001);
+
+        let data: &[u8] = &[
+            1, 0, 42, // before any image identifies itself
+            0xff, 0xff, 0x01, 0x00, 0x01, 0x08, // app
+            1, 0, 43, //
+            0xff, 0xff, 0x01, 0x01, 0x00, 0x08, // bootloader, after a reset
+            1, 0, 44, //
+            0xff, 0xff, 0x01, 0x00, 0x00, 0x09, // unknown image
+        ];
+        let frames = Frames::from_images(vec![bootloader.clone(), app.clone()], data)
+            .unwrap()
+            .map(|frame| message(frame.unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            frames,
+            [
+                Ok("bootloader x=42".to_owned()),
+                Ok("app x=43".to_owned()),
+                Ok("bootloader x=44".to_owned()),
+                Err(DecodeError::Malformed),
+            ]
+        );
+
+        // with a single image, the frames that identify images are skipped
+        let frames = bootloader
+            .frames(data)
+            .map(|frame| message(frame.unwrap()).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            frames,
+            ["bootloader x=42", "bootloader x=43", "bootloader x=44"]
+        );
+        let mut decoder = bootloader.new_stream_decoder();
+        decoder.received(data);
+        for expected in ["bootloader x=42", "bootloader x=43", "bootloader x=44"] {
+            assert_eq!(
+                decoder.decode().unwrap().display_message().to_string(),
+                expected
+            );
+        }
+        assert_eq!(decoder.decode().unwrap_err(), DecodeError::UnexpectedEof);
+
+        // no images, or images with different encodings
+        assert!(Frames::from_images(vec![], data).is_err());
+        let rzcobs = Arc::new(Table::with_entries(&[], Encoding::Rzcobs));
+        assert!(Frames::from_images(vec![app, rzcobs], data).is_err());
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn async_frames() {
+        use futures_util::StreamExt as _;
+        use tokio::io::AsyncWriteExt as _;
+
+        let (mut device, host) = tokio::io::duplex(64);
+        let mut frames = table().frames_async(host);
+
+        // the first frame arrives in 2 parts
+        device.write_all(&[0, 1, 42]).await.unwrap();
+        device.write_all(&[0x7a, 0, 0x80, 0]).await.unwrap();
+        let frame = frames.next().await.unwrap().unwrap();
+        assert_eq!(message(frame).unwrap(), "x=42");
+
+        // a malformed frame is skipped
+        let frame = frames.next().await.unwrap().unwrap();
+        assert_eq!(message(frame), Err(DecodeError::Malformed));
+
+        device.write_all(&[1, 43, 0x7a, 0, 1]).await.unwrap();
+        drop(device);
+        let frame = frames.next().await.unwrap().unwrap();
+        assert_eq!(message(frame).unwrap(), "x=43");
+        assert!(frames.next().await.is_none());
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn async_undecodable_frames() {
+        use futures_util::StreamExt as _;
+
+        // a frame with an unknown index, between 2 good ones
+        let data: &[u8] = &[0, 1, 42, 0x7a, 0, 9, 42, 0x7a, 0, 1, 43, 0x7a, 0];
+        let frames = table()
+            .frames_async(data)
+            .map(|frame| message(frame.unwrap()))
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(
+            frames,
+            [
+                Ok("x=42".to_owned()),
+                Err(DecodeError::Malformed),
+                Ok("x=43".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn owned_frames_are_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<OwnedFrame>();
+    }
+}
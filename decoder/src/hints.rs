@@ -0,0 +1,101 @@
+//! Formatters for custom display hints.
+//!
+//! The firmware marks a parameter with a custom hint, e.g. `{=u16:@reg_status}`, and the host
+//! registers a formatter for `reg_status` in [`HintFormatters`]. Parameters with a custom hint
+//! that has no registered formatter are formatted as if there was no hint.
+//!
+//! The `@` is required: the macros reject a hint without it, like `{=u16:reg_status}`, as an
+//! unknown display hint.
+
+use std::{collections::HashMap, fmt};
+
+use crate::Arg;
+
+type Formatter = dyn Fn(HintValue<'_>, &mut dyn fmt::Write) -> fmt::Result + Send + Sync;
+
+/// A decoded value that is formatted by a custom display hint formatter.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum HintValue<'a> {
+    Bool(bool),
+    F32(f32),
+    F64(f64),
+    /// Unsigned integers, including bitfields
+    Uxx(u128),
+    /// Signed integers
+    Ixx(i128),
+    Str(&'a str),
+    /// Byte slices and arrays
+    Bytes(&'a [u8]),
+    Char(char),
+}
+
+impl<'a> HintValue<'a> {
+    /// Returns `None` for values that are composed of other values, like `Format` arguments.
+    pub(crate) fn from_arg(arg: &'a Arg<'_>) -> Option<Self> {
+        Some(match arg {
+            Arg::Bool(x) => HintValue::Bool(*x),
+            Arg::F32(x) => HintValue::F32(*x),
+            Arg::F64(x) => HintValue::F64(*x),
+            Arg::Uxx(x) => HintValue::Uxx(*x),
+            Arg::Ixx(x) => HintValue::Ixx(*x),
+            Arg::Str(x) | Arg::Preformatted(x) => HintValue::Str(x),
+            Arg::IStr(x) => HintValue::Str(x),
+            Arg::Slice(x) => HintValue::Bytes(x),
+            Arg::Char(x) => HintValue::Char(*x),
+            Arg::Format { .. } | Arg::FormatSequence { .. } | Arg::FormatSlice { .. } => {
+                return None
+            }
+        })
+    }
+}
+
+/// A registry of formatters for custom display hints.
+///
+/// Apply it by passing it to `with_hint_formatters` of the value returned by
+/// [`Frame::display`](crate::Frame::display), [`Frame::display_message`](crate::Frame::display_message)
+/// or [`Frame::display_timestamp`](crate::Frame::display_timestamp).
+///
+/// ```
+/// use std::fmt::Write as _;
+///
+/// use defmt_decoder::{HintFormatters, HintValue};
+///
+/// let mut formatters = HintFormatters::new();
+/// formatters.register("reg_status", |value, f| match value {
+///     HintValue::Uxx(x) => write!(f, "READY={} ERROR={}", x & 1, (x >> 1) & 1),
+///     _ => write!(f, "{value:?}"),
+/// });
+/// ```
+#[derive(Default)]
+pub struct HintFormatters {
+    formatters: HashMap<String, Box<Formatter>>,
+}
+
+impl HintFormatters {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `formatter` for the hint `@name`, replacing any previously registered one.
+    ///
+    /// `name` is given without the `@`, which the format string needs in front of it.
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        formatter: impl Fn(HintValue<'_>, &mut dyn fmt::Write) -> fmt::Result + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.formatters.insert(name.into(), Box::new(formatter));
+        self
+    }
+
+    pub(crate) fn get(&self, name: &str) -> Option<&Formatter> {
+        self.formatters.get(name).map(|formatter| &**formatter)
+    }
+}
+
+impl fmt::Debug for HintFormatters {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.formatters.keys()).finish()
+    }
+}
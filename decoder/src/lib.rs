@@ -12,30 +12,48 @@
 
 pub const DEFMT_VERSION: &str = "3";
 
+mod catalog;
 mod decoder;
+mod diff;
 mod elf2table;
+#[cfg(feature = "exit")]
+pub mod exit;
 mod frame;
+mod frames;
+mod hints;
 pub mod log;
+mod parallel;
+mod size;
 mod stream;
+#[cfg(feature = "tracing")]
+pub mod tracing;
 
 use std::{
     collections::{BTreeMap, HashMap},
     error::Error,
     fmt, io,
     str::FromStr,
+    sync::Arc,
 };
 
-use byteorder::{ReadBytesExt, LE};
 use decoder::Decoder;
 use defmt_parser::Level;
 use elf2table::parse_impl;
 
-pub use elf2table::{Location, Locations};
+pub use catalog::CatalogEntry;
+pub use diff::{EntryDiff, TableDiff};
+pub use elf2table::{defmt_function_sizes, find_debug_file, LazyLocations, Location, Locations};
 pub use frame::Frame;
+#[cfg(feature = "async")]
+pub use frames::AsyncFrames;
+pub use frames::{Frames, OwnedFrame};
+pub use hints::{HintFormatters, HintValue};
+pub use parallel::ParallelFrames;
+pub use size::ArgsSize;
 pub use stream::StreamDecoder;
 
 /// Specifies the origin of a format string
-#[derive(PartialEq, Eq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum Tag {
     /// Defmt-controlled format string for primitive types.
     Prim,
@@ -63,7 +81,7 @@ pub enum Tag {
 }
 
 impl Tag {
-    fn to_level(&self) -> Option<Level> {
+    fn to_level(self) -> Option<Level> {
         match self {
             Tag::Trace => Some(Level::Trace),
             Tag::Debug => Some(Level::Debug),
@@ -107,6 +125,14 @@ impl StringEntry {
     pub fn new(tag: Tag, string: String) -> Self {
         Self { tag, string }
     }
+
+    pub fn tag(&self) -> Tag {
+        self.tag
+    }
+
+    pub fn string(&self) -> &str {
+        &self.string
+    }
 }
 
 /// Data that uniquely identifies a `defmt::bitflags!` invocation.
@@ -123,6 +149,8 @@ struct BitflagsKey {
 pub enum Encoding {
     Raw,
     Rzcobs,
+    RzcobsCrc,
+    Lzss,
 }
 
 impl FromStr for Encoding {
@@ -132,6 +160,8 @@ impl FromStr for Encoding {
         match s {
             "raw" => Ok(Encoding::Raw),
             "rzcobs" => Ok(Encoding::Rzcobs),
+            "rzcobs-crc" => Ok(Encoding::RzcobsCrc),
+            "lzss" => Ok(Encoding::Lzss),
             _ => anyhow::bail!("Unknown defmt encoding '{}' specified. This is a bug.", s),
         }
     }
@@ -142,10 +172,22 @@ impl Encoding {
         match self {
             Encoding::Raw => false,
             Encoding::Rzcobs => true,
+            Encoding::RzcobsCrc => true,
+            Encoding::Lzss => true,
         }
     }
 }
 
+/// How integers, lengths and interned string indices are encoded in log frames
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum IntegerEncoding {
+    /// Little endian, with the width of their type
+    Fixed,
+    /// LEB128, signed integers zigzag-encoded; `u8` and `i8` are still a single byte
+    Varint,
+}
+
 /// Internal table that holds log levels and maps format strings to indices
 #[derive(Debug, Eq, PartialEq)]
 pub struct Table {
@@ -153,6 +195,8 @@ pub struct Table {
     entries: BTreeMap<usize, TableEntry>,
     bitflags: HashMap<BitflagsKey, Vec<(String, u128)>>,
     encoding: Encoding,
+    integer_encoding: IntegerEncoding,
+    image_id: Option<u32>,
 }
 
 impl Table {
@@ -163,6 +207,37 @@ impl Table {
         parse_impl(elf, true)
     }
 
+    /// Creates a table from its entries, for data that did not come from an ELF file, e.g. in
+    /// benchmarks. Integers are expected to be fixed-width.
+    ///
+    /// This is not part of the stable API.
+    #[doc(hidden)]
+    pub fn new(entries: BTreeMap<usize, TableEntry>, encoding: Encoding) -> Self {
+        Self {
+            timestamp: None,
+            entries,
+            bitflags: Default::default(),
+            encoding,
+            integer_encoding: IntegerEncoding::Fixed,
+            image_id: None,
+        }
+    }
+
+    /// Creates a table with the format strings of `entries` at their index, for tests.
+    #[cfg(test)]
+    pub(crate) fn with_entries(entries: &[(usize, Tag, &str)], encoding: Encoding) -> Self {
+        let entries = entries
+            .iter()
+            .map(|&(index, tag, string)| {
+                (
+                    index,
+                    TableEntry::new_without_symbol(tag, string.to_owned()),
+                )
+            })
+            .collect();
+        Self::new(entries, encoding)
+    }
+
     /// Like `parse`, but does not verify that the defmt version in the firmware matches the host.
     ///
     /// CAUTION: This is meant for defmt/probe-run development only and can result in reading garbage data.
@@ -192,6 +267,11 @@ impl Table {
         }
     }
 
+    /// Returns the format string at `index`, and its tag.
+    pub fn entry(&self, index: usize) -> Option<&StringEntry> {
+        self.entries.get(&index).map(|entry| &entry.string)
+    }
+
     pub fn indices(&self) -> impl Iterator<Item = usize> + '_ {
         self.entries.iter().filter_map(move |(idx, entry)| {
             if entry.string.tag.to_level().is_some() || entry.string.tag == Tag::Println {
@@ -211,8 +291,17 @@ impl Table {
         self.entries.values().map(|s| &*s.raw_symbol)
     }
 
+    /// Reads the locations of the log statements from the debug info in `elf`.
+    ///
+    /// If the debug info was moved to a separate file, pass the contents of that file instead,
+    /// see [`find_debug_file`].
     pub fn get_locations(&self, elf: &[u8]) -> Result<Locations, anyhow::Error> {
-        elf2table::get_locations(elf, self)
+        LazyLocations::new(elf, self)?.resolve_all()
+    }
+
+    /// Like [`Table::get_locations`], but looks up each location when it is first used.
+    pub fn lazy_locations(&self, elf: &[u8]) -> Result<LazyLocations, anyhow::Error> {
+        LazyLocations::new(elf, self)
     }
 
     /// Decode the data sent by the device using the previously stored metadata.
@@ -222,12 +311,11 @@ impl Table {
     ///   * contains the [log string index, timestamp, optional fmt string args]
     pub fn decode<'t>(
         &'t self,
-        mut bytes: &[u8],
+        bytes: &[u8],
     ) -> Result<(Frame<'t>, /* consumed: */ usize), DecodeError> {
         let len = bytes.len();
-        let index = bytes.read_u16::<LE>()? as u64;
-
         let mut decoder = Decoder::new(self, bytes);
+        let index = decoder.read_unsigned(16)? as u64;
 
         let mut timestamp_format = None;
         let mut timestamp_args = Vec::new();
@@ -258,15 +346,70 @@ impl Table {
     }
 
     pub fn new_stream_decoder(&self) -> Box<dyn StreamDecoder + '_> {
-        match self.encoding {
-            Encoding::Raw => Box::new(stream::Raw::new(self)),
-            Encoding::Rzcobs => Box::new(stream::Rzcobs::new(self)),
-        }
+        Box::new(stream::Decoder::new(self))
+    }
+
+    /// Decodes the frames read from `reader`, returning them as [`OwnedFrame`]s.
+    ///
+    /// This is an alternative to [`Table::new_stream_decoder`] for when the frames need to outlive
+    /// the decoder, or when the data comes from an [`io::Read`]er anyway.
+    pub fn frames<R: io::Read>(self: &Arc<Self>, reader: R) -> Frames<R> {
+        Frames::new(self.clone(), reader)
+    }
+
+    /// Decodes the frames in `data`, e.g. a file of captured data, on multiple threads.
+    ///
+    /// The frames are returned in order, like [`Table::new_stream_decoder`] would. Only encodings
+    /// whose frames can be found without decoding the previous ones, like `rzcobs`, are decoded
+    /// in parallel; other encodings are decoded on the current thread.
+    pub fn decode_parallel<'d>(&self, data: &'d [u8]) -> ParallelFrames<'_, 'd> {
+        ParallelFrames::new(self, data)
+    }
+
+    /// Returns the worst case of the encoded size of the arguments of the format string at `index`.
+    pub fn args_size(&self, index: usize) -> Option<ArgsSize> {
+        let entry = self.entries.get(&index)?;
+        size::args_size(&entry.string.string, self.integer_encoding)
+    }
+
+    /// Returns the log and `println!` statements of the firmware, sorted by module.
+    ///
+    /// Log statements whose location is not in `locations` come last.
+    pub fn catalog<'a>(&'a self, locations: &'a Locations) -> Vec<CatalogEntry<'a>> {
+        catalog::catalog(self, locations)
+    }
+
+    /// Compares the entries of this table, of an older build of the firmware, with those of `new`.
+    ///
+    /// Add the locations of the log statements with [`TableDiff::with_locations`] to tell reused
+    /// indices of log statements that moved apart.
+    pub fn diff<'a>(&'a self, new: &'a Table) -> TableDiff<'a> {
+        TableDiff::new(self, new)
+    }
+
+    /// Like [`Table::frames`], but reads from a tokio `AsyncRead`er and returns a `Stream`.
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub fn frames_async<R: tokio::io::AsyncRead + Unpin>(
+        self: &Arc<Self>,
+        reader: R,
+    ) -> AsyncFrames<R> {
+        AsyncFrames::new(self.clone(), reader)
     }
 
     pub fn encoding(&self) -> Encoding {
         self.encoding
     }
+
+    pub fn integer_encoding(&self) -> IntegerEncoding {
+        self.integer_encoding
+    }
+
+    /// Returns the ID the firmware identifies its image with, if it was built with the `image-id`
+    /// feature of `defmt`.
+    pub fn image_id(&self) -> Option<u32> {
+        self.image_id
+    }
 }
 
 // NOTE follows `parser::Type`
@@ -313,11 +456,15 @@ struct FormatSliceElement<'t> {
 }
 
 #[derive(Debug, Eq, PartialEq)]
+#[non_exhaustive]
 pub enum DecodeError {
     /// More data is needed to decode the next frame.
     UnexpectedEof,
 
     Malformed,
+
+    /// The frame was decoded, but its checksum doesn't match, so it was corrupted on the wire.
+    ChecksumMismatch,
 }
 
 impl From<io::Error> for DecodeError {
@@ -335,6 +482,7 @@ impl fmt::Display for DecodeError {
         match self {
             DecodeError::UnexpectedEof => f.write_str("unexpected end of stream"),
             DecodeError::Malformed => f.write_str("malformed data"),
+            DecodeError::ChecksumMismatch => f.write_str("checksum mismatch"),
         }
     }
 }
@@ -351,6 +499,8 @@ mod tests {
             entries: entries.into_iter().enumerate().collect(),
             bitflags: Default::default(),
             encoding: Encoding::Raw,
+            integer_encoding: IntegerEncoding::Fixed,
+            image_id: None,
         }
     }
 
@@ -366,6 +516,8 @@ mod tests {
             entries: entries.into_iter().enumerate().collect(),
             bitflags: Default::default(),
             encoding: Encoding::Raw,
+            integer_encoding: IntegerEncoding::Fixed,
+            image_id: None,
         }
     }
 
@@ -389,6 +541,8 @@ mod tests {
             )),
             bitflags: Default::default(),
             encoding: Encoding::Raw,
+            integer_encoding: IntegerEncoding::Fixed,
+            image_id: None,
         };
 
         let frame = table.decode(bytes).unwrap().0;
@@ -447,6 +601,46 @@ mod tests {
         // TODO Format ({:?})
     }
 
+    #[test]
+    fn decode_varint() {
+        let entries = vec![
+            TableEntry::new_without_symbol(Tag::Info, "Hello, world!".to_owned()),
+            TableEntry::new_without_symbol(
+                Tag::Info,
+                "{=u8} {=u16} {=i32} {=str} {=u64} {=i8}".to_owned(),
+            ),
+        ];
+
+        let mut table = test_table_with_timestamp(entries, "{=u32:us}");
+        table.integer_encoding = IntegerEncoding::Varint;
+
+        #[rustfmt::skip]
+        let bytes = [
+            1, // index
+            0xac, 0x02, // timestamp 300
+            200, // u8 values are not LEB128-encoded
+            1, // u16 value
+            0x81, 0x01, // i32 value -65, zigzag-encoded as 129
+            2, b'h', b'i', // length and bytes of the string
+            0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01, // u64::MAX
+            0xff, // i8 values are not zigzag-encoded
+        ];
+
+        let (frame, consumed) = table.decode(&bytes).unwrap();
+        assert_eq!(consumed, bytes.len());
+        assert_eq!(
+            frame.display(false).to_string(),
+            "0.000300 INFO 200 1 -65 hi 18446744073709551615 -1"
+        );
+
+        // the `u16` index takes at most 3 bytes
+        let bytes = [0x80, 0x80, 0x80, 0x00, 0];
+        assert_eq!(table.decode(&bytes), Err(DecodeError::Malformed));
+        // and the 3rd byte has only 2 significant bits
+        let bytes = [0xff, 0xff, 0x04, 0];
+        assert_eq!(table.decode(&bytes), Err(DecodeError::Malformed));
+    }
+
     #[test]
     fn all_integers() {
         const FMT: &str =
@@ -664,6 +858,7 @@ mod tests {
             frame.display(false).to_string(),
             "0.000002 INFO x=Foo { x: 42 }"
         );
+        assert_eq!(frame.timestamp(), Some(2));
     }
 
     #[test]
@@ -684,6 +879,155 @@ mod tests {
         );
     }
 
+    #[test]
+    fn display_width_alignment_precision() {
+        let bytes = [
+            0, 0, // index
+            2, // timestamp
+            0x00, 0x00, 0xc0, 0x3f, // the logged f32 value 1.5
+            42, 0, 0, 0, // the logged u32 value 42
+            5, 0, 0, 0, // length of the string
+            b'H', b'e', b'l', b'l', b'o', // string "Hello"
+            7, 0, // the logged i16 value 7
+        ];
+
+        decode_and_expect(
+            "{=f32:.3}|{=u32:>8}|{=str:<8}|{=i16:+}",
+            &bytes,
+            "0.000002 INFO 1.500|      42|Hello   |+7",
+        );
+        decode_and_expect(
+            "{=f32:+}|{=u32:^6}|{=str:.3}|{=i16:^+6}",
+            &bytes,
+            "0.000002 INFO +1.5|  42  |Hel|  +7  ",
+        );
+        // without alignment, numbers are right-aligned and everything else left-aligned
+        decode_and_expect(
+            "{=f32:8.2}|{=u32:4}|{=str:7}|{=i16:03}",
+            &bytes,
+            "0.000002 INFO     1.50|  42|Hello  |007",
+        );
+    }
+
+    #[test]
+    fn display_width_pads_whole_struct() {
+        let entries = vec![
+            TableEntry::new_without_symbol(Tag::Info, "[{:>12}]".to_owned()),
+            TableEntry::new_without_symbol(Tag::Derived, "S {{ x: {=u8} }}".to_owned()),
+        ];
+
+        let table = test_table_with_timestamp(entries, "{=u8:us}");
+
+        let bytes = [
+            0, 0, // index
+            2, // timestamp
+            1, 0,  // index of the struct
+            42, // value
+        ];
+
+        let frame = table.decode(&bytes).unwrap().0;
+        assert_eq!(
+            frame.display(false).to_string(),
+            "0.000002 INFO [ S { x: 42 }]",
+        );
+    }
+
+    #[test]
+    fn display_network_hints() {
+        let bytes = [
+            0, 0, // index
+            2, // timestamp
+            0x01, 0x00, 0xa8, 0xc0, // the logged u32 value 0xc0a80001
+            192, 168, 0, 2, // the logged [u8; 4]
+            0x02, 0x00, 0x5e, 0x10, 0x00, 0x01, // the logged [u8; 6]
+        ];
+
+        decode_and_expect(
+            "{=u32:ipv4} {=[u8; 4]:ipv4} {=[u8; 6]:mac}",
+            &bytes,
+            "0.000002 INFO 192.168.0.1 192.168.0.2 02:00:5e:10:00:01",
+        );
+
+        let bytes = [
+            0, 0, // index
+            2, // timestamp
+            0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, // the logged [u8; 16]
+        ];
+
+        decode_and_expect("{=[u8; 16]:ipv6}", &bytes, "0.000002 INFO 2001:db8::1");
+        decode_and_expect(
+            "{=[u8; 16]:uuid}",
+            &bytes,
+            "0.000002 INFO 20010db8-0000-0000-0000-000000000001",
+        );
+    }
+
+    #[test]
+    fn display_hexdump_hint() {
+        let mut bytes = vec![
+            0, 0, // index
+            2, // timestamp
+            18, 0, 0, 0, // length of the slice
+        ];
+        bytes.extend_from_slice(b"Hello, world!\n\x00\x01\xffz");
+
+        decode_and_expect(
+            "packet:\n{=[u8]:hexdump}",
+            &bytes,
+            "0.000002 INFO packet:\n\
+             00000000: 48 65 6c 6c 6f 2c 20 77 6f 72 6c 64 21 0a 00 01  Hello, world!...\n\
+             00000010: ff 7a                                            .z",
+        );
+    }
+
+    #[test]
+    fn display_domain_hint_with_wrong_length() {
+        let bytes = [
+            0, 0, // index
+            2, // timestamp
+            3, 0, 0, 0, // length of the slice
+            1, 2, 3, // the logged [u8]
+        ];
+
+        decode_and_expect("{=[u8]:mac}", &bytes, "0.000002 INFO [1, 2, 3]");
+    }
+
+    #[test]
+    fn display_custom_hint() {
+        let entries = vec![TableEntry::new_without_symbol(
+            Tag::Info,
+            "{=u16:@reg_status} {=0..4:@reg_status} {=str:@unregistered}".to_owned(),
+        )];
+        let table = test_table_with_timestamp(entries, "{=u8:us}");
+
+        #[rustfmt::skip]
+        let bytes = [
+            0, 0, // index
+            2, // timestamp
+            0b1010_0110, 0, // the logged u16 value
+            0b0000_0010, // the bitfields
+            2, 0, 0, 0, // length of the string
+            b'o', b'k', // string "ok"
+        ];
+
+        let mut formatters = HintFormatters::new();
+        formatters.register("reg_status", |value, f| match value {
+            HintValue::Uxx(x) => write!(f, "READY={} ERROR={}", x & 1, (x >> 1) & 1),
+            _ => unreachable!(),
+        });
+
+        let frame = table.decode(&bytes).unwrap().0;
+        assert_eq!(
+            frame
+                .display(false)
+                .with_hint_formatters(&formatters)
+                .to_string(),
+            "0.000002 INFO READY=0 ERROR=1 READY=0 ERROR=1 ok",
+        );
+        // without formatters custom hints are ignored
+        assert_eq!(frame.display_message().to_string(), "166 2 ok");
+    }
+
     #[test]
     fn display_use_inner_type_hint() {
         let entries = vec![
@@ -1089,6 +1433,8 @@ mod tests {
             )),
             bitflags: Default::default(),
             encoding: Encoding::Raw,
+            integer_encoding: IntegerEncoding::Fixed,
+            image_id: None,
         };
 
         let bytes = [
@@ -0,0 +1,326 @@
+use anyhow::anyhow;
+use colored::Color;
+use log::Level;
+
+use std::{
+    env,
+    io::{self, IsTerminal},
+    str::FromStr,
+};
+
+use super::Hyperlinks;
+
+/// When to color the output, see [`PrettyFormatter::color`](super::PrettyFormatter::color) and
+/// [`LoggerOptions::color`](super::LoggerOptions::color).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// Color the output if it is written to a terminal and the `NO_COLOR` environment variable is
+    /// not set. The logger looks at stdout for defmt frames and at stderr for host messages.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl FromStr for ColorChoice {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(ColorChoice::Auto),
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            _ => Err(anyhow!(
+                "unknown color choice `{s}`, expected auto, always or never"
+            )),
+        }
+    }
+}
+
+impl ColorChoice {
+    /// Returns whether to color the output written to `stream`.
+    fn should_colorize(self, stream: impl IsTerminal) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                // see https://no-color.org
+                let no_color = env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+                !no_color && stream.is_terminal()
+            }
+        }
+    }
+}
+
+/// The colors of log levels and module paths, see [`PrettyFormatter::theme`](super::PrettyFormatter::theme)
+/// and [`LoggerOptions::theme`](super::LoggerOptions::theme).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Theme {
+    error: Color,
+    warn: Color,
+    info: Color,
+    debug: Color,
+    trace: Color,
+    module_colors: bool,
+}
+
+const DEFAULT_THEME: Theme = Theme {
+    error: Color::Red,
+    warn: Color::Yellow,
+    info: Color::Green,
+    debug: Color::BrightWhite,
+    trace: Color::BrightBlack,
+    module_colors: false,
+};
+
+/// Colors module paths are picked from, by their hash
+const MODULE_COLORS: &[Color] = &[
+    Color::Cyan,
+    Color::Magenta,
+    Color::Blue,
+    Color::BrightCyan,
+    Color::BrightMagenta,
+    Color::BrightBlue,
+    Color::BrightYellow,
+    Color::BrightGreen,
+];
+
+impl Default for Theme {
+    fn default() -> Self {
+        DEFAULT_THEME
+    }
+}
+
+impl Theme {
+    /// Sets the color of `level`.
+    pub fn level_color(mut self, level: Level, color: Color) -> Self {
+        *match level {
+            Level::Error => &mut self.error,
+            Level::Warn => &mut self.warn,
+            Level::Info => &mut self.info,
+            Level::Debug => &mut self.debug,
+            Level::Trace => &mut self.trace,
+        } = color;
+        self
+    }
+
+    /// Configure whether to color module paths, each in a color picked by a hash of its path, so
+    /// that a module has the same color across runs.
+    ///
+    /// By default, this is `false`.
+    pub fn module_colors(mut self, module_colors: bool) -> Self {
+        self.module_colors = module_colors;
+        self
+    }
+}
+
+/// The color of a log level, as `<level>=<color>`, e.g. `info=blue`.
+///
+/// The colors are those of the `colored` crate, like `red` or `bright black`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LevelColor {
+    pub level: Level,
+    pub color: Color,
+}
+
+impl FromStr for LevelColor {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (level, color) = s
+            .split_once('=')
+            .ok_or_else(|| anyhow!("expected `<level>=<color>`, found `{s}`"))?;
+        Ok(Self {
+            level: level.parse()?,
+            color: color
+                .parse()
+                .map_err(|()| anyhow!("unknown color `{color}`"))?,
+        })
+    }
+}
+
+/// Colors text with a [`Theme`], if the [`ColorChoice`] is to color it, and knows whether to write
+/// hyperlinks.
+///
+/// Unlike the `colored` crate, this does not depend on global state.
+#[derive(Clone, Debug)]
+pub(super) struct Painter {
+    colorize: bool,
+    theme: Theme,
+    hyperlinks: Option<Hyperlinks>,
+}
+
+/// How to style text, see [`Painter::paint`].
+#[derive(Clone, Copy, Debug, Default)]
+pub(super) struct Style {
+    pub(super) color: Option<Color>,
+    pub(super) bold: bool,
+    pub(super) dimmed: bool,
+}
+
+impl Style {
+    pub(super) fn color(color: Color) -> Self {
+        Self {
+            color: Some(color),
+            ..Self::default()
+        }
+    }
+
+    pub(super) fn bold(mut self) -> Self {
+        self.bold = true;
+        self
+    }
+
+    pub(super) fn dimmed(mut self) -> Self {
+        self.dimmed = true;
+        self
+    }
+}
+
+impl Default for Painter {
+    fn default() -> Self {
+        Self::new(ColorChoice::default(), Theme::default(), None)
+    }
+}
+
+impl Painter {
+    pub(super) fn new(choice: ColorChoice, theme: Theme, hyperlinks: Option<Hyperlinks>) -> Self {
+        Self {
+            colorize: choice.should_colorize(io::stdout()),
+            theme,
+            hyperlinks,
+        }
+    }
+
+    /// Returns a painter like this one for output to stderr, which `choice` may color differently
+    /// than stdout.
+    pub(super) fn for_stderr(&self, choice: ColorChoice) -> Self {
+        Self {
+            colorize: choice.should_colorize(io::stderr()),
+            ..self.clone()
+        }
+    }
+
+    pub(super) fn set_color_choice(&mut self, choice: ColorChoice) {
+        self.colorize = choice.should_colorize(io::stdout());
+    }
+
+    pub(super) fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+
+    pub(super) fn set_hyperlinks(&mut self, hyperlinks: Option<Hyperlinks>) {
+        self.hyperlinks = hyperlinks;
+    }
+
+    /// Returns whether the output is colored.
+    pub(super) fn colorize(&self) -> bool {
+        self.colorize
+    }
+
+    pub(super) fn hyperlinks(&self) -> Option<&Hyperlinks> {
+        self.hyperlinks.as_ref()
+    }
+
+    /// Writes `text` in `style` with ANSI escape codes, if the output is colored.
+    pub(super) fn paint(&self, text: &str, style: Style) -> String {
+        let mut codes = Vec::new();
+        if style.bold {
+            codes.push("1".into());
+        }
+        if style.dimmed {
+            codes.push("2".into());
+        }
+        if let Some(color) = style.color {
+            codes.push(color.to_fg_str());
+        }
+
+        match self.colorize && !codes.is_empty() {
+            true => format!("\x1b[{}m{text}\x1b[0m", codes.join(";")),
+            false => text.to_string(),
+        }
+    }
+
+    /// Colors `text` in the color of `level`.
+    pub(super) fn level(&self, text: &str, level: Level) -> String {
+        let color = match level {
+            Level::Error => self.theme.error,
+            Level::Warn => self.theme.warn,
+            Level::Info => self.theme.info,
+            Level::Debug => self.theme.debug,
+            Level::Trace => self.theme.trace,
+        };
+        self.paint(text, Style::color(color))
+    }
+
+    /// Colors `module` by its hash if enabled by the theme, and applies `style` otherwise.
+    pub(super) fn module(&self, module: &str, style: Style) -> String {
+        match self.theme.module_colors {
+            true => self.paint(
+                module,
+                Style {
+                    color: Some(module_color(module)),
+                    ..style
+                },
+            ),
+            false => self.paint(module, style),
+        }
+    }
+}
+
+fn module_color(module: &str) -> Color {
+    // FNV-1a, which unlike the hasher of the standard library is the same across releases
+    let hash = module.bytes().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    MODULE_COLORS[(hash % MODULE_COLORS.len() as u64) as usize]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            "warn=bright magenta".parse::<LevelColor>().unwrap(),
+            LevelColor {
+                level: Level::Warn,
+                color: Color::BrightMagenta,
+            }
+        );
+        assert!("warn".parse::<LevelColor>().is_err());
+        assert!("loud=red".parse::<LevelColor>().is_err());
+        assert!("warn=plaid".parse::<LevelColor>().is_err());
+        assert_eq!("never".parse::<ColorChoice>().unwrap(), ColorChoice::Never);
+    }
+
+    #[test]
+    fn paint() {
+        let painter = Painter::new(
+            ColorChoice::Always,
+            Theme::default().level_color(Level::Info, Color::Blue),
+            None,
+        );
+        assert_eq!(painter.level("INFO", Level::Info), "\x1b[34mINFO\x1b[0m");
+        assert_eq!(
+            painter.paint("a", Style::color(Color::Red).bold()),
+            "\x1b[1;31ma\x1b[0m"
+        );
+        assert_eq!(painter.paint("a", Style::default()), "a");
+
+        let painter = Painter::new(
+            ColorChoice::Never,
+            Theme::default().module_colors(true),
+            None,
+        );
+        assert_eq!(painter.level("INFO", Level::Info), "INFO");
+        assert_eq!(painter.module("app", Style::default().dimmed()), "app");
+    }
+
+    #[test]
+    fn module_colors_are_stable() {
+        assert_eq!(module_color("app::main"), module_color("app::main"));
+        assert_eq!(module_color(""), MODULE_COLORS[5]);
+    }
+}
@@ -0,0 +1,290 @@
+use defmt_json_schema::v1::{JsonFrame, Location as JsonLocation};
+
+use std::{
+    io::{self, Write},
+    sync::atomic::AtomicUsize,
+};
+
+use super::{
+    color::Painter,
+    json_logger::{create_module_path, host_timestamp, write_schema_version},
+    log_format::{Fields, LogFormat},
+    pretty_logger::{align_timestamp, print_location, write_defmt_frame, write_println_frame},
+    to_log_level, ColorChoice, Hyperlinks, Theme,
+};
+use crate::{Frame, Location};
+
+/// Renders frames in the human-readable format used by `defmt-print`, without going through the
+/// `log` facade.
+///
+/// The format is as follows (this is not part of the stable API and may change):
+///
+/// ```text
+/// <timestamp> <level> <args>
+/// └─ <function> @ <file>:<line>:<column>
+/// ```
+///
+/// The module is shown instead of the function and the column is left out if they are not known.
+/// A [`LogFormat`] can replace this layout.
+#[derive(Debug)]
+pub struct PrettyFormatter {
+    include_location: bool,
+    log_format: Option<LogFormat>,
+    painter: Painter,
+    /// Number of characters used by the timestamp. This may increase over time and is used to align
+    /// messages.
+    timing_align: AtomicUsize,
+}
+
+impl Default for PrettyFormatter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PrettyFormatter {
+    pub fn new() -> Self {
+        Self {
+            include_location: true,
+            log_format: None,
+            painter: Painter::default(),
+            timing_align: AtomicUsize::new(0),
+        }
+    }
+
+    /// Configure whether to include location info (module, file, line) in the output.
+    ///
+    /// If `true`, an additional line will be written for frames whose location is known.
+    /// By default, this is `true`.
+    pub fn include_location(mut self, include_location: bool) -> Self {
+        self.include_location = include_location;
+        self
+    }
+
+    /// Writes the frames with `log_format` instead of the default layout.
+    ///
+    /// The location is then only written where the template has location fields, regardless of
+    /// [`include_location`](Self::include_location).
+    pub fn log_format(mut self, log_format: LogFormat) -> Self {
+        self.log_format = Some(log_format);
+        self
+    }
+
+    /// Configure when to color the output.
+    ///
+    /// By default, this is [`ColorChoice::Auto`], which looks at stdout even if the sink is
+    /// something else.
+    pub fn color(mut self, choice: ColorChoice) -> Self {
+        self.painter.set_color_choice(choice);
+        self
+    }
+
+    /// Colors the output with `theme`.
+    pub fn theme(mut self, theme: Theme) -> Self {
+        self.painter.set_theme(theme);
+        self
+    }
+
+    /// Writes the location of log statements as `hyperlinks`, which a click in the terminal
+    /// opens, if the output is colored.
+    ///
+    /// The file of the [`Location`] is used as the path, so it should be absolute.
+    pub fn hyperlinks(mut self, hyperlinks: Hyperlinks) -> Self {
+        self.painter.set_hyperlinks(Some(hyperlinks));
+        self
+    }
+
+    /// Writes `frame`, and its `location` if it is known, to `sink`.
+    ///
+    /// Timestamps are padded to the width of the widest timestamp written so far.
+    pub fn write_frame(
+        &self,
+        sink: &mut impl Write,
+        frame: &Frame<'_>,
+        location: Option<&Location>,
+    ) -> io::Result<()> {
+        let timestamp = frame
+            .display_timestamp()
+            .map(|ts| ts.to_string())
+            .unwrap_or_default();
+        let message = frame.display_message().to_string();
+
+        if let Some(log_format) = &self.log_format {
+            let file = location.map(|location| location.file.display().to_string());
+            let fields = Fields {
+                min_timestamp_width: align_timestamp(&self.timing_align, &timestamp),
+                timestamp: &timestamp,
+                level: frame.level().map(to_log_level),
+                message,
+                module_path: location
+                    .map(|location| &**location.function.as_ref().unwrap_or(&location.module)),
+                file: file.as_deref(),
+                path: file.as_deref(),
+                line: location.map(|location| location.line as u32),
+                column: location.and_then(|location| location.column.map(|column| column as u32)),
+            };
+            return log_format.write(sink, &self.painter, &fields);
+        }
+
+        match frame.level() {
+            Some(level) => {
+                let min_timestamp_width = align_timestamp(&self.timing_align, &timestamp);
+                write_defmt_frame(
+                    sink,
+                    &self.painter,
+                    &timestamp,
+                    to_log_level(level),
+                    message,
+                    min_timestamp_width,
+                )?;
+            }
+            None => write_println_frame(sink, &timestamp, &message)?,
+        }
+
+        if let (true, Some(location)) = (self.include_location, location) {
+            let file = location.file.display().to_string();
+            print_location(
+                sink,
+                &self.painter,
+                Some(&file),
+                Some(&file),
+                Some(location.line as u32),
+                location.column.map(|column| column as u32),
+                Some(location.function.as_ref().unwrap_or(&location.module)),
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Renders frames as JSON lines in the format of `defmt-json-schema`, without going through the
+/// `log` facade.
+#[derive(Debug, Default)]
+pub struct JsonFormatter {}
+
+impl JsonFormatter {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// Writes the schema version, which consumers expect on the first line of the output.
+    pub fn write_schema_version(&self, sink: &mut impl Write) -> io::Result<()> {
+        write_schema_version(sink)
+    }
+
+    /// Writes `frame`, and its `location` if it is known, as one line of JSON to `sink`.
+    ///
+    /// The host timestamp is the time this method is called.
+    pub fn write_frame(
+        &self,
+        sink: &mut impl Write,
+        frame: &Frame<'_>,
+        location: Option<&Location>,
+    ) -> io::Result<()> {
+        let json_frame = JsonFrame {
+            data: frame.display_message().to_string(),
+            host_timestamp: host_timestamp(),
+            level: frame.level().map(to_log_level),
+            location: JsonLocation {
+                file: location.map(|location| location.file.display().to_string()),
+                line: location.map(|location| location.line as u32),
+                module_path: create_module_path(location.map(|location| &*location.module)),
+            },
+            target_timestamp: frame
+                .display_timestamp()
+                .map(|ts| ts.to_string())
+                .unwrap_or_default(),
+        };
+
+        serde_json::to_writer(&mut *sink, &json_frame)?;
+        writeln!(sink)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use defmt_json_schema::{v1::SCHEMA_VERSION, SchemaVersion};
+
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::{Encoding, Table, Tag};
+
+    fn table() -> Table {
+        Table::with_entries(
+            &[(0, Tag::Info, "x={=u8}"), (1, Tag::Println, "hello")],
+            Encoding::Raw,
+        )
+    }
+
+    fn location() -> Location {
+        Location {
+            file: PathBuf::from("src/main.rs"),
+            line: 42,
+            column: Some(5),
+            module: "app::main".to_owned(),
+            function: None,
+        }
+    }
+
+    #[test]
+    fn pretty() {
+        let table = table();
+        let formatter = PrettyFormatter::new().color(ColorChoice::Never);
+
+        let mut out = Vec::new();
+        let (frame, _) = table.decode(&[0, 0, 7]).unwrap();
+        formatter
+            .write_frame(&mut out, &frame, Some(&location()))
+            .unwrap();
+        let (frame, _) = table.decode(&[1, 0]).unwrap();
+        formatter.write_frame(&mut out, &frame, None).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "INFO  x=7\n└─ app::main @ src/main.rs:42:5\nhello\n"
+        );
+    }
+
+    #[test]
+    fn pretty_log_format() {
+        let table = table();
+        let formatter = PrettyFormatter::new()
+            .color(ColorChoice::Never)
+            .log_format("[{L}] {s} ({f}:{l})".parse().unwrap());
+
+        let mut out = Vec::new();
+        let (frame, _) = table.decode(&[0, 0, 7]).unwrap();
+        formatter
+            .write_frame(&mut out, &frame, Some(&location()))
+            .unwrap();
+
+        assert_eq!(String::from_utf8(out).unwrap(), "[INFO] x=7 (main.rs:42)\n");
+    }
+
+    #[test]
+    fn json() {
+        let table = table();
+        let formatter = JsonFormatter::new();
+
+        let mut out = Vec::new();
+        formatter.write_schema_version(&mut out).unwrap();
+        let (frame, _) = table.decode(&[0, 0, 7]).unwrap();
+        formatter
+            .write_frame(&mut out, &frame, Some(&location()))
+            .unwrap();
+
+        let out = String::from_utf8(out).unwrap();
+        let mut lines = out.lines();
+        let schema_version: SchemaVersion = serde_json::from_str(lines.next().unwrap()).unwrap();
+        assert_eq!(schema_version, SCHEMA_VERSION);
+        let json_frame: JsonFrame = serde_json::from_str(lines.next().unwrap()).unwrap();
+        assert_eq!(json_frame.data, "x=7");
+        assert_eq!(json_frame.level, Some(log::Level::Info));
+        assert_eq!(json_frame.location.file.as_deref(), Some("src/main.rs"));
+        assert_eq!(json_frame.location.line, Some(42));
+        assert_eq!(json_frame.location.module_path.unwrap().function, "main");
+        assert!(lines.next().is_none());
+    }
+}
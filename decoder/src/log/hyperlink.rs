@@ -0,0 +1,125 @@
+use anyhow::bail;
+
+use std::str::FromStr;
+
+use super::color::Painter;
+
+/// The URL of hyperlinks to the location of log statements, see
+/// [`PrettyFormatter::hyperlinks`](super::PrettyFormatter::hyperlinks) and
+/// [`LoggerOptions::hyperlinks`](super::LoggerOptions::hyperlinks).
+///
+/// The hyperlinks are OSC 8 escape sequences, which terminals without support for them ignore.
+/// Like colors, they are only written if the output is colored, see
+/// [`ColorChoice`](super::ColorChoice).
+///
+/// The URL is a template with the placeholders `{path}` for the absolute path of the file,
+/// `{line}` for the line and `{column}` for the column (`1` if it's not known), e.g.
+/// `vscode://file/{path}:{line}:{column}` to open the log statement in VS Code. If `{path}` comes
+/// after a `/` like there, the leading `/` of the path is left out. By default, it is
+/// `file://{path}:{line}`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Hyperlinks {
+    template: String,
+}
+
+impl Default for Hyperlinks {
+    fn default() -> Self {
+        Self {
+            template: "file://{path}:{line}".to_string(),
+        }
+    }
+}
+
+impl FromStr for Hyperlinks {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if !s.contains("{path}") {
+            bail!("hyperlink template `{s}` has no `{{path}}`");
+        }
+        Ok(Self {
+            template: s.to_string(),
+        })
+    }
+}
+
+/// Makes `text` a hyperlink to `line` and `column` of the file at the absolute `path`, if enabled.
+pub(super) fn hyperlink(
+    painter: &Painter,
+    text: String,
+    path: Option<&str>,
+    line: Option<u32>,
+    column: Option<u32>,
+) -> String {
+    let (hyperlinks, path) = match (painter.hyperlinks(), path) {
+        (Some(hyperlinks), Some(path)) if painter.colorize() => (hyperlinks, path),
+        _ => return text,
+    };
+
+    hyperlinks.link(&text, path, line, column)
+}
+
+impl Hyperlinks {
+    fn link(&self, text: &str, path: &str, line: Option<u32>, column: Option<u32>) -> String {
+        let path = encode_path(path);
+        // a `/` before `{path}`, like in `vscode://file/{path}`, already separates it from what
+        // comes before, unless it ends the `//` of a URL without a host, like `file://{path}`
+        let path = match self.template.contains("/{path}") && !self.template.contains("://{path}") {
+            true => path.strip_prefix('/').unwrap_or(&path),
+            false => &path,
+        };
+        let url = self
+            .template
+            .replace("{path}", path)
+            .replace("{line}", &line.unwrap_or(1).to_string())
+            .replace("{column}", &column.unwrap_or(1).to_string());
+        format!("\x1b]8;;{url}\x1b\\{text}\x1b]8;;\x1b\\")
+    }
+}
+
+/// Percent-encodes the characters of `path` that are not allowed in the path of a URL.
+fn encode_path(path: &str) -> String {
+    let mut encoded = String::new();
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => {
+                encoded.push(byte as char)
+            }
+            // Windows paths
+            b'\\' => encoded.push('/'),
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode() {
+        assert_eq!(
+            encode_path("/home/me/my app/src/main.rs"),
+            "/home/me/my%20app/src/main.rs"
+        );
+        assert_eq!(encode_path(r"C:\fw\ä.rs"), "C:/fw/%C3%A4.rs");
+    }
+
+    #[test]
+    fn link() {
+        let hyperlinks = "vscode://file/{path}:{line}:{column}"
+            .parse::<Hyperlinks>()
+            .unwrap();
+        assert_eq!(
+            hyperlinks.link("src/main.rs:42", "/fw/src/main.rs", Some(42), None),
+            "\x1b]8;;vscode://file/fw/src/main.rs:42:1\x1b\\src/main.rs:42\x1b]8;;\x1b\\"
+        );
+        assert_eq!(
+            Hyperlinks::default().link("a", "/a.rs", Some(1), Some(2)),
+            "\x1b]8;;file:///a.rs:1\x1b\\a\x1b]8;;\x1b\\"
+        );
+
+        assert!("vscode://file".parse::<Hyperlinks>().is_err());
+    }
+}
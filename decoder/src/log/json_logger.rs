@@ -4,7 +4,7 @@ use time::OffsetDateTime;
 
 use std::io::{self, Write};
 
-use super::{pretty_logger::PrettyLogger, DefmtRecord};
+use super::{pretty_logger::PrettyLogger, DefmtRecord, LoggerOptions};
 
 pub(crate) struct JsonLogger {
     should_log: Box<dyn Fn(&Metadata) -> bool + Sync + Send>,
@@ -25,10 +25,7 @@ impl Log for JsonLogger {
             // defmt goes to stdout, since it's the primary output produced by this tool.
             let mut sink = io::stdout().lock();
 
-            let host_timestamp = OffsetDateTime::now_utc()
-                .unix_timestamp_nanos()
-                .min(i64::MAX as i128) as i64;
-            serde_json::to_writer(&mut sink, &create_json_frame(record, host_timestamp)).ok();
+            serde_json::to_writer(&mut sink, &create_json_frame(record, host_timestamp())).ok();
             writeln!(sink).ok();
         } else {
             // non-defmt logs go to stderr
@@ -41,20 +38,34 @@ impl Log for JsonLogger {
 }
 
 impl JsonLogger {
-    pub fn new(should_log: impl Fn(&Metadata) -> bool + Sync + Send + 'static) -> Box<Self> {
+    /// Host logs are printed like by the `PrettyLogger`, with the colors of `options`.
+    pub fn new(
+        options: LoggerOptions,
+        should_log: impl Fn(&Metadata) -> bool + Sync + Send + 'static,
+    ) -> Box<Self> {
         Box::new(Self {
             should_log: Box::new(should_log),
-            host_logger: PrettyLogger::new_unboxed(true, |_| true),
+            host_logger: PrettyLogger::new_unboxed(options.always_include_location(true), |_| true),
         })
     }
 
     pub fn print_schema_version() {
-        let mut sink = io::stdout().lock();
-        serde_json::to_writer(&mut sink, &SCHEMA_VERSION).ok();
-        writeln!(sink).ok();
+        write_schema_version(&mut io::stdout().lock()).ok();
     }
 }
 
+pub(super) fn write_schema_version<W: Write>(sink: &mut W) -> io::Result<()> {
+    serde_json::to_writer(&mut *sink, &SCHEMA_VERSION)?;
+    writeln!(sink)
+}
+
+/// Unix timestamp in nanoseconds
+pub(super) fn host_timestamp() -> i64 {
+    OffsetDateTime::now_utc()
+        .unix_timestamp_nanos()
+        .min(i64::MAX as i128) as i64
+}
+
 /// Create a new [JsonFrame] from a log-frame from the target
 fn create_json_frame(record: DefmtRecord, host_timestamp: i64) -> JsonFrame {
     JsonFrame {
@@ -70,7 +81,7 @@ fn create_json_frame(record: DefmtRecord, host_timestamp: i64) -> JsonFrame {
     }
 }
 
-fn create_module_path(module_path: Option<&str>) -> Option<ModulePath> {
+pub(super) fn create_module_path(module_path: Option<&str>) -> Option<ModulePath> {
     let mut path = module_path?.split("::").collect::<Vec<_>>();
 
     // there need to be at least two elements, the crate and the function
@@ -0,0 +1,294 @@
+use log::Level;
+
+use anyhow::{anyhow, bail};
+
+use std::{io, path::Path, str::FromStr};
+
+use super::{
+    color::{Painter, Style},
+    hyperlink::hyperlink,
+    pretty_logger::color_diff,
+};
+
+/// A template for the lines written for each defmt frame, instead of the default layout.
+///
+/// The template is text with placeholders for the fields of the frame:
+///
+/// - `{t}`: the timestamp
+/// - `{L}`: the level, which is empty for frames from `defmt::println!`
+/// - `{s}`: the message
+/// - `{m}`: the function, or the module if the function is not known
+/// - `{F}`: the path of the file
+/// - `{f}`: the name of the file
+/// - `{l}`: the line
+/// - `{c}`: the column
+///
+/// Like in Rust format strings, a placeholder can set the minimum width and the alignment of its
+/// field, e.g. `{t:>10}`, `{L:<5}` or `{m:^20}`. Timestamps are right-aligned to the widest
+/// timestamp written so far unless a width is set, the other fields are left-aligned. Fields that
+/// are not known, like the location of a log statement without debug info, are empty, and so is a
+/// `:` right before them, so that `{l}:{c}` is just the line if the column is not known. `{{` and
+/// `}}` are written as `{` and `}`, and newlines in the template start a new line.
+///
+/// For example, `{t} {L:5} {s}\n└─ {m} @ {F}:{l}` is close to the default layout.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LogFormat {
+    segments: Vec<Segment>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Field {
+        field: Field,
+        alignment: Option<Alignment>,
+        width: Option<usize>,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Field {
+    Timestamp,
+    Level,
+    Message,
+    Module,
+    File,
+    FileName,
+    Line,
+    Column,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Alignment {
+    Left,
+    Center,
+    Right,
+}
+
+/// The fields of a frame, see [`LogFormat`].
+pub(super) struct Fields<'a> {
+    pub(super) timestamp: &'a str,
+    /// Width of the widest timestamp written so far
+    pub(super) min_timestamp_width: usize,
+    pub(super) level: Option<Level>,
+    pub(super) message: String,
+    pub(super) module_path: Option<&'a str>,
+    pub(super) file: Option<&'a str>,
+    /// Absolute path of `file`, which hyperlinks point to
+    pub(super) path: Option<&'a str>,
+    pub(super) line: Option<u32>,
+    pub(super) column: Option<u32>,
+}
+
+impl FromStr for LogFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut chars = s.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let rest = chars.as_str();
+                    let end = rest
+                        .find('}')
+                        .ok_or_else(|| anyhow!("unterminated placeholder in log format `{s}`"))?;
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                    }
+                    segments.push(parse_placeholder(&rest[..end])?);
+                    chars = rest[end + 1..].chars();
+                }
+                '}' => bail!("unmatched `}}` in log format `{s}`, use `}}}}` for a literal `}}`"),
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+
+        Ok(Self { segments })
+    }
+}
+
+/// Parses a placeholder without its braces, e.g. `t:>10`.
+fn parse_placeholder(placeholder: &str) -> Result<Segment, anyhow::Error> {
+    let (name, spec) = placeholder.split_once(':').unwrap_or((placeholder, ""));
+    let field = match name {
+        "t" => Field::Timestamp,
+        "L" => Field::Level,
+        "s" => Field::Message,
+        "m" => Field::Module,
+        "F" => Field::File,
+        "f" => Field::FileName,
+        "l" => Field::Line,
+        "c" => Field::Column,
+        _ => bail!("unknown log format field `{{{placeholder}}}`, expected one of t, L, s, m, F, f, l and c"),
+    };
+
+    let (alignment, width) = match spec.chars().next() {
+        Some('<') => (Some(Alignment::Left), &spec[1..]),
+        Some('^') => (Some(Alignment::Center), &spec[1..]),
+        Some('>') => (Some(Alignment::Right), &spec[1..]),
+        _ => (None, spec),
+    };
+    let width = match width {
+        "" => None,
+        width => Some(
+            width
+                .parse()
+                .map_err(|_| anyhow!("invalid width in log format field `{{{placeholder}}}`"))?,
+        ),
+    };
+
+    Ok(Segment::Field {
+        field,
+        alignment,
+        width,
+    })
+}
+
+impl LogFormat {
+    /// Writes the lines of a frame with the given `fields` to `sink`.
+    pub(super) fn write<W: io::Write>(
+        &self,
+        sink: &mut W,
+        painter: &Painter,
+        fields: &Fields,
+    ) -> io::Result<()> {
+        let mut out = String::new();
+        let mut last_segment = None;
+        for segment in &self.segments {
+            let previous = last_segment.replace(segment);
+            let (field, alignment, width) = match segment {
+                Segment::Literal(literal) => {
+                    out.push_str(literal);
+                    continue;
+                }
+                Segment::Field {
+                    field,
+                    alignment,
+                    width,
+                } => (*field, *alignment, *width),
+            };
+
+            let text = match field {
+                Field::Timestamp => fields.timestamp.to_string(),
+                Field::Level => fields.level.map(|l| l.to_string()).unwrap_or_default(),
+                Field::Message => fields.message.clone(),
+                Field::Module => fields.module_path.unwrap_or_default().to_string(),
+                Field::File => fields.file.unwrap_or_default().to_string(),
+                Field::FileName => fields
+                    .file
+                    .and_then(|file| Path::new(file).file_name())
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+                Field::Line => fields.line.map(|l| l.to_string()).unwrap_or_default(),
+                Field::Column => fields.column.map(|c| c.to_string()).unwrap_or_default(),
+            };
+            // leave out the separator of a field that is not known, e.g. in `{l}:{c}`
+            if let (true, Some(Segment::Literal(literal))) = (text.is_empty(), previous) {
+                if literal.ends_with(':') {
+                    out.pop();
+                }
+            }
+            let (alignment, width) = match (field, width) {
+                (Field::Timestamp, None) => (
+                    alignment.unwrap_or(Alignment::Right),
+                    fields.min_timestamp_width,
+                ),
+                _ => (alignment.unwrap_or(Alignment::Left), width.unwrap_or(0)),
+            };
+            // pad before coloring, the escape codes would count towards the width
+            let text = match alignment {
+                Alignment::Left => format!("{text:<width$}"),
+                Alignment::Center => format!("{text:^width$}"),
+                Alignment::Right => format!("{text:>width$}"),
+            };
+
+            match (field, fields.level) {
+                (Field::Level, Some(level)) => out.push_str(&painter.level(&text, level)),
+                (Field::Message, Some(_)) => out.push_str(&color_diff(painter, text)),
+                (Field::Module, _) => out.push_str(&painter.module(&text, Style::default())),
+                (Field::File | Field::FileName, _) => out.push_str(&hyperlink(
+                    painter,
+                    text,
+                    fields.path,
+                    fields.line,
+                    fields.column,
+                )),
+                _ => out.push_str(&text),
+            }
+        }
+
+        writeln!(sink, "{out}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log::{ColorChoice, Theme};
+
+    fn fields() -> Fields<'static> {
+        Fields {
+            timestamp: "1.5",
+            min_timestamp_width: 4,
+            level: Some(Level::Warn),
+            message: "x=7".to_string(),
+            module_path: Some("app::main"),
+            file: Some("src/main.rs"),
+            path: Some("/fw/src/main.rs"),
+            line: Some(42),
+            column: None,
+        }
+    }
+
+    fn write(format: &str, fields: &Fields) -> String {
+        let painter = Painter::new(ColorChoice::Never, Theme::default(), None);
+        let mut out = Vec::new();
+        format
+            .parse::<LogFormat>()
+            .unwrap()
+            .write(&mut out, &painter, fields)
+            .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn write_fields() {
+        assert_eq!(
+            write("{t:>6} [{L}] {m}: {s} ({f}:{l}:{c})", &fields()),
+            "   1.5 [WARN] app::main: x=7 (main.rs:42)\n"
+        );
+        assert_eq!(
+            write("{t} {L:5}|{s:^5}|\n{{{F}}}", &fields()),
+            " 1.5 WARN | x=7 |\n{src/main.rs}\n"
+        );
+
+        let println = Fields {
+            level: None,
+            module_path: None,
+            file: None,
+            line: None,
+            ..fields()
+        };
+        assert_eq!(write("{L:<5}{s} @ {F}:{l}", &println), "     x=7 @ \n");
+    }
+
+    #[test]
+    fn parse_errors() {
+        for format in ["{t", "{x}", "{t:>x}", "a}b"] {
+            assert!(format.parse::<LogFormat>().is_err(), "{format}");
+        }
+    }
+}
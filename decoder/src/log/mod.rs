@@ -3,10 +3,17 @@
 //! If you are implementing a custom defmt decoding tool, this module can make it easier to
 //! integrate it with logs produced with the [`log`] crate.
 //!
+//! To render frames without installing a global logger, use [`PrettyFormatter`] or
+//! [`JsonFormatter`] instead.
+//!
 //! [`log`]: https://crates.io/crates/log
 //! [`defmt`]: https://crates.io/crates/defmt
 
+mod color;
+mod format;
+mod hyperlink;
 mod json_logger;
+mod log_format;
 mod pretty_logger;
 
 use log::{Level, LevelFilter, Metadata, Record};
@@ -14,6 +21,12 @@ use serde::{Deserialize, Serialize};
 
 use std::fmt;
 
+pub use self::{
+    color::{ColorChoice, LevelColor, Theme},
+    format::{JsonFormatter, PrettyFormatter},
+    hyperlink::Hyperlinks,
+    log_format::LogFormat,
+};
 use self::{json_logger::JsonLogger, pretty_logger::PrettyLogger};
 use crate::Frame;
 
@@ -26,23 +39,54 @@ pub fn log_defmt(
     line: Option<u32>,
     module_path: Option<&str>,
 ) {
+    log_defmt_at(
+        frame,
+        &DefmtLocation {
+            file,
+            line,
+            module_path,
+            ..Default::default()
+        },
+    )
+}
+
+/// The location of a log statement, as passed to [`log_defmt_at`].
+///
+/// Fields may be added in the future, so construct it with `..Default::default()`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DefmtLocation<'a> {
+    /// The file as displayed, e.g. relative to the current directory
+    pub file: Option<&'a str>,
+    /// The absolute path of `file`, which hyperlinks point to, see [`LoggerOptions::hyperlinks`]
+    pub path: Option<&'a str>,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+    pub module_path: Option<&'a str>,
+    /// The function, which is displayed instead of the module path if it is known
+    pub function: Option<&'a str>,
+}
+
+/// Logs a defmt frame using the `log` facade, like [`log_defmt`], with more details about the
+/// location of its log statement.
+pub fn log_defmt_at(frame: &Frame<'_>, location: &DefmtLocation<'_>) {
     let timestamp = frame
         .display_timestamp()
         .map(|ts| ts.to_string())
         .unwrap_or_default();
 
-    let level = frame.level().map(|level| match level {
-        crate::Level::Trace => Level::Trace,
-        crate::Level::Debug => Level::Debug,
-        crate::Level::Info => Level::Info,
-        crate::Level::Warn => Level::Warn,
-        crate::Level::Error => Level::Error,
-    });
+    let level = frame.level().map(to_log_level);
 
     let target = format!(
         "{}{}",
         DEFMT_TARGET_MARKER,
-        serde_json::to_value(Payload { timestamp, level }).unwrap()
+        serde_json::to_value(Payload {
+            timestamp,
+            level,
+            column: location.column,
+            path: location.path.map(ToString::to_string),
+            function: location.function.map(ToString::to_string),
+        })
+        .unwrap()
     );
 
     log::logger().log(
@@ -50,13 +94,23 @@ pub fn log_defmt(
             .args(format_args!("{}", frame.display_message()))
             // .level(level) // no need to set the level, since it is transferred via payload
             .target(&target)
-            .module_path(module_path)
-            .file(file)
-            .line(line)
+            .module_path(location.module_path)
+            .file(location.file)
+            .line(location.line)
             .build(),
     );
 }
 
+fn to_log_level(level: crate::Level) -> Level {
+    match level {
+        crate::Level::Trace => Level::Trace,
+        crate::Level::Debug => Level::Debug,
+        crate::Level::Info => Level::Info,
+        crate::Level::Warn => Level::Warn,
+        crate::Level::Error => Level::Error,
+    }
+}
+
 /// Determines whether `metadata` belongs to a log record produced by [`log_defmt`].
 pub fn is_defmt_frame(metadata: &Metadata) -> bool {
     metadata.target().starts_with(DEFMT_TARGET_MARKER)
@@ -72,6 +126,9 @@ pub struct DefmtRecord<'a> {
 struct Payload {
     level: Option<Level>,
     timestamp: String,
+    column: Option<u32>,
+    path: Option<String>,
+    function: Option<String>,
 }
 
 impl<'a> DefmtRecord<'a> {
@@ -110,6 +167,19 @@ impl<'a> DefmtRecord<'a> {
     pub fn line(&self) -> Option<u32> {
         self.log_record.line()
     }
+
+    pub fn column(&self) -> Option<u32> {
+        self.payload.column
+    }
+
+    /// Returns the absolute path of the [`file`](Self::file).
+    pub fn path(&self) -> Option<&str> {
+        self.payload.path.as_deref()
+    }
+
+    pub fn function(&self) -> Option<&str> {
+        self.payload.function.as_deref()
+    }
 }
 
 /// Initializes a `log` sink that handles defmt frames.
@@ -122,16 +192,95 @@ impl<'a> DefmtRecord<'a> {
 /// If `always_include_location` is `true`, a second line containing location information will be
 /// printed for *all* records, not just for defmt frames (defmt frames always get location info
 /// included if it is available, regardless of this setting).
+///
+/// See [`init_logger_with_options`] for more options.
 pub fn init_logger(
     always_include_location: bool,
     json: bool,
     should_log: impl Fn(&Metadata) -> bool + Sync + Send + 'static,
 ) {
-    log::set_boxed_logger(match json {
-        false => PrettyLogger::new(always_include_location, should_log),
+    init_logger_with_options(
+        LoggerOptions::new()
+            .always_include_location(always_include_location)
+            .json(json),
+        should_log,
+    )
+}
+
+/// Options of the logger initialized by [`init_logger_with_options`].
+#[derive(Debug, Default)]
+pub struct LoggerOptions {
+    always_include_location: bool,
+    json: bool,
+    log_format: Option<LogFormat>,
+    color: ColorChoice,
+    theme: Theme,
+    hyperlinks: Option<Hyperlinks>,
+}
+
+impl LoggerOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Configure whether to print a second line containing location information for *all*
+    /// records, not just for defmt frames (defmt frames always get location info included if it is
+    /// available, regardless of this setting).
+    ///
+    /// By default, this is `false`.
+    pub fn always_include_location(mut self, always_include_location: bool) -> Self {
+        self.always_include_location = always_include_location;
+        self
+    }
+
+    /// Configure whether to print defmt frames as JSON lines, see [`JsonFormatter`].
+    ///
+    /// By default, this is `false`.
+    pub fn json(mut self, json: bool) -> Self {
+        self.json = json;
+        self
+    }
+
+    /// Prints defmt frames with `log_format` instead of the default layout. It is ignored for JSON
+    /// output.
+    pub fn log_format(mut self, log_format: LogFormat) -> Self {
+        self.log_format = Some(log_format);
+        self
+    }
+
+    /// Configure when to color the output.
+    ///
+    /// By default, this is [`ColorChoice::Auto`].
+    pub fn color(mut self, choice: ColorChoice) -> Self {
+        self.color = choice;
+        self
+    }
+
+    /// Colors the output with `theme`.
+    pub fn theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    /// Writes the location of log statements as `hyperlinks`, which a click in the terminal
+    /// opens, if the output is colored. They point to the path given to [`log_defmt_at`].
+    pub fn hyperlinks(mut self, hyperlinks: Hyperlinks) -> Self {
+        self.hyperlinks = Some(hyperlinks);
+        self
+    }
+}
+
+/// Initializes a `log` sink that handles defmt frames, like [`init_logger`], with the given
+/// `options`.
+pub fn init_logger_with_options(
+    options: LoggerOptions,
+    should_log: impl Fn(&Metadata) -> bool + Sync + Send + 'static,
+) {
+    log::set_boxed_logger(match options.json {
+        false => PrettyLogger::new(options, should_log),
         true => {
             JsonLogger::print_schema_version();
-            JsonLogger::new(should_log)
+            JsonLogger::new(options, should_log)
         }
     })
     .unwrap();
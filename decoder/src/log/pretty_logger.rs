@@ -1,4 +1,4 @@
-use colored::{Color, Colorize};
+use colored::Color;
 use dissimilar::Chunk;
 use log::{Level, Log, Metadata, Record};
 
@@ -8,10 +8,20 @@ use std::{
     sync::atomic::{AtomicUsize, Ordering},
 };
 
-use super::DefmtRecord;
+use super::{
+    color::{Painter, Style},
+    hyperlink::hyperlink,
+    log_format::{Fields, LogFormat},
+    DefmtRecord, LoggerOptions,
+};
 
 pub(crate) struct PrettyLogger {
     always_include_location: bool,
+    /// Replaces the default layout of defmt frames
+    log_format: Option<LogFormat>,
+    painter: Painter,
+    /// Colors host messages, which go to stderr
+    host_painter: Painter,
     should_log: Box<dyn Fn(&Metadata) -> bool + Sync + Send>,
     /// Number of characters used by the timestamp. This may increase over time and is used to align
     /// messages.
@@ -35,7 +45,7 @@ impl Log for PrettyLogger {
 
                 match record.level() {
                     Some(level) => self.print_defmt_record(record, level, sink),
-                    None => Self::print_println_record(record, sink),
+                    None => self.print_println_record(record, sink),
                 };
             }
             None => {
@@ -51,29 +61,42 @@ impl Log for PrettyLogger {
 
 impl PrettyLogger {
     pub fn new(
-        always_include_location: bool,
+        options: LoggerOptions,
         should_log: impl Fn(&Metadata) -> bool + Sync + Send + 'static,
     ) -> Box<Self> {
-        Box::new(Self::new_unboxed(always_include_location, should_log))
+        Box::new(Self::new_unboxed(options, should_log))
     }
 
     pub fn new_unboxed(
-        always_include_location: bool,
+        options: LoggerOptions,
         should_log: impl Fn(&Metadata) -> bool + Sync + Send + 'static,
     ) -> Self {
+        let painter = Painter::new(options.color, options.theme, options.hyperlinks);
         Self {
-            always_include_location,
+            always_include_location: options.always_include_location,
+            log_format: options.log_format,
+            host_painter: painter.for_stderr(options.color),
+            painter,
             should_log: Box::new(should_log),
             timing_align: AtomicUsize::new(0),
         }
     }
 
     fn print_defmt_record(&self, record: DefmtRecord, level: Level, mut sink: StdoutLock) {
-        let len = record.timestamp().len();
-        self.timing_align.fetch_max(len, Ordering::Relaxed);
-        let min_timestamp_width = self.timing_align.load(Ordering::Relaxed);
+        let min_timestamp_width = align_timestamp(&self.timing_align, record.timestamp());
+
+        if let Some(log_format) = &self.log_format {
+            log_format
+                .write(
+                    &mut sink,
+                    &self.painter,
+                    &record_fields(&record, min_timestamp_width),
+                )
+                .ok();
+            return;
+        }
 
-        Printer::new(&record, level)
+        Printer::new(&record, level, &self.painter)
             .include_location(true) // always include location for defmt output
             .min_timestamp_width(min_timestamp_width)
             .print_colored(&mut sink)
@@ -88,10 +111,9 @@ impl PrettyLogger {
             "{timestamp:>0$} {level:5} {args}",
             min_timestamp_width,
             timestamp = "(HOST)",
-            level = record
-                .level()
-                .to_string()
-                .color(color_for_log_level(record.level())),
+            level = self
+                .host_painter
+                .level(&record.level().to_string(), record.level()),
             args = record.args()
         )
         .ok();
@@ -99,46 +121,75 @@ impl PrettyLogger {
         if self.always_include_location {
             print_location(
                 &mut sink,
+                &self.host_painter,
                 record.file(),
+                None,
                 record.line(),
+                None,
                 record.module_path(),
             )
             .ok();
         }
     }
 
-    fn print_println_record(record: DefmtRecord, mut sink: StdoutLock) {
-        let timestamp = match record.timestamp().is_empty() {
-            true => record.timestamp().to_string(),
-            false => format!("{} ", record.timestamp()),
-        };
+    fn print_println_record(&self, record: DefmtRecord, mut sink: StdoutLock) {
+        if let Some(log_format) = &self.log_format {
+            let min_timestamp_width = align_timestamp(&self.timing_align, record.timestamp());
+            log_format
+                .write(
+                    &mut sink,
+                    &self.painter,
+                    &record_fields(&record, min_timestamp_width),
+                )
+                .ok();
+            return;
+        }
 
-        writeln!(&mut sink, "{timestamp}{}", record.args()).ok();
+        write_println_frame(&mut sink, record.timestamp(), &record.args().to_string()).ok();
         print_location(
             &mut sink,
+            &self.painter,
             record.file(),
+            record.path(),
             record.line(),
-            record.module_path(),
+            record.column(),
+            record.function().or(record.module_path()),
         )
         .ok();
     }
 }
 
+fn record_fields<'a>(record: &'a DefmtRecord, min_timestamp_width: usize) -> Fields<'a> {
+    Fields {
+        timestamp: record.timestamp(),
+        min_timestamp_width,
+        level: record.level(),
+        message: record.args().to_string(),
+        module_path: record.function().or(record.module_path()),
+        file: record.file(),
+        path: record.path(),
+        line: record.line(),
+        column: record.column(),
+    }
+}
+
 /// Printer for `DefmtRecord`s.
 pub struct Printer<'a> {
     record: &'a DefmtRecord<'a>,
     include_location: bool,
     level: Level,
     min_timestamp_width: usize,
+    painter: &'a Painter,
 }
 
 impl<'a> Printer<'a> {
-    pub fn new(record: &'a DefmtRecord, level: Level) -> Self {
+    pub fn new(record: &'a DefmtRecord, level: Level, painter: &'a Painter) -> Self {
         Self {
             record,
             include_location: false,
             level,
             min_timestamp_width: 0,
+            painter,
         }
     }
 
@@ -164,33 +215,27 @@ impl<'a> Printer<'a> {
     ///
     /// ```text
     /// <timestamp> <level> <args>
-    /// └─ <module> @ <file>:<line>
+    /// └─ <module> @ <file>:<line>:<column>
     /// ```
     pub fn print_colored<W: io::Write>(&self, sink: &mut W) -> io::Result<()> {
-        writeln!(
+        write_defmt_frame(
             sink,
-            "{timestamp:>0$}{spacing}{level:5} {args}",
+            self.painter,
+            self.record.timestamp(),
+            self.level,
+            self.record.args().to_string(),
             self.min_timestamp_width,
-            timestamp = self.record.timestamp(),
-            spacing = if self.record.timestamp().is_empty() {
-                ""
-            } else {
-                " "
-            },
-            level = self
-                .level
-                .to_string()
-                .color(color_for_log_level(self.level)),
-            args = color_diff(self.record.args().to_string()),
         )?;
 
         if self.include_location {
-            let log_record = self.record.log_record;
             print_location(
                 sink,
-                log_record.file(),
-                log_record.line(),
-                log_record.module_path(),
+                self.painter,
+                self.record.file(),
+                self.record.path(),
+                self.record.line(),
+                self.record.column(),
+                self.record.function().or(self.record.module_path()),
             )?;
         }
 
@@ -198,10 +243,49 @@ impl<'a> Printer<'a> {
     }
 }
 
+/// Widens `timing_align` to fit `timestamp`, and returns the new width.
+pub(super) fn align_timestamp(timing_align: &AtomicUsize, timestamp: &str) -> usize {
+    timing_align.fetch_max(timestamp.len(), Ordering::Relaxed);
+    timing_align.load(Ordering::Relaxed)
+}
+
+/// Writes the line of a frame with a log level.
+pub(super) fn write_defmt_frame<W: io::Write>(
+    sink: &mut W,
+    painter: &Painter,
+    timestamp: &str,
+    level: Level,
+    message: String,
+    min_timestamp_width: usize,
+) -> io::Result<()> {
+    writeln!(
+        sink,
+        "{timestamp:>0$}{spacing}{level:5} {args}",
+        min_timestamp_width,
+        spacing = if timestamp.is_empty() { "" } else { " " },
+        level = painter.level(&level.to_string(), level),
+        args = color_diff(painter, message),
+    )
+}
+
+/// Writes the line of a frame without a log level, from `defmt::println!`.
+pub(super) fn write_println_frame<W: io::Write>(
+    sink: &mut W,
+    timestamp: &str,
+    message: &str,
+) -> io::Result<()> {
+    let timestamp = match timestamp.is_empty() {
+        true => timestamp.to_string(),
+        false => format!("{timestamp} "),
+    };
+
+    writeln!(sink, "{timestamp}{message}")
+}
+
 // color the output of `defmt::assert_eq`
 // HACK we should not re-parse formatted output but instead directly format into a color diff
 // template; that may require specially tagging log messages that come from `defmt::assert_eq`
-fn color_diff(text: String) -> String {
+pub(super) fn color_diff(painter: &Painter, text: String) -> String {
     let lines = text.lines().collect::<Vec<_>>();
     let nlines = lines.len();
     if nlines > 2 {
@@ -220,7 +304,9 @@ fn color_diff(text: String) -> String {
             let left = &left[LEFT_START.len()..left.len() - END.len()];
             let right = &right[RIGHT_START.len()..right.len() - END.len()];
 
-            let mut buf = lines[..nlines - 2].join("\n").bold().to_string();
+            let red = Style::color(Color::Red);
+            let green = Style::color(Color::Green);
+            let mut buf = painter.paint(&lines[..nlines - 2].join("\n"), Style::default().bold());
             buf.push('\n');
 
             let diffs = dissimilar::diff(left, right);
@@ -228,34 +314,34 @@ fn color_diff(text: String) -> String {
             writeln!(
                 buf,
                 "{} {} / {}",
-                "diff".bold(),
-                "< left".red(),
-                "right >".green()
+                painter.paint("diff", Style::default().bold()),
+                painter.paint("< left", red),
+                painter.paint("right >", green)
             )
             .ok();
-            write!(buf, "{}", "<".red()).ok();
+            write!(buf, "{}", painter.paint("<", red)).ok();
             for diff in &diffs {
                 match diff {
                     Chunk::Equal(s) => {
-                        write!(buf, "{}", s.red()).ok();
+                        write!(buf, "{}", painter.paint(s, red)).ok();
                     }
                     Chunk::Insert(_) => continue,
                     Chunk::Delete(s) => {
-                        write!(buf, "{}", s.red().bold()).ok();
+                        write!(buf, "{}", painter.paint(s, red.bold())).ok();
                     }
                 }
             }
             buf.push('\n');
 
-            write!(buf, "{}", ">".green()).ok();
+            write!(buf, "{}", painter.paint(">", green)).ok();
             for diff in &diffs {
                 match diff {
                     Chunk::Equal(s) => {
-                        write!(buf, "{}", s.green()).ok();
+                        write!(buf, "{}", painter.paint(s, green)).ok();
                     }
                     Chunk::Delete(_) => continue,
                     Chunk::Insert(s) => {
-                        write!(buf, "{}", s.green().bold()).ok();
+                        write!(buf, "{}", painter.paint(s, green.bold())).ok();
                     }
                 }
             }
@@ -264,23 +350,18 @@ fn color_diff(text: String) -> String {
     }
 
     // keep output as it is
-    text.bold().to_string()
-}
-
-fn color_for_log_level(level: Level) -> Color {
-    match level {
-        Level::Error => Color::Red,
-        Level::Warn => Color::Yellow,
-        Level::Info => Color::Green,
-        Level::Debug => Color::BrightWhite,
-        Level::Trace => Color::BrightBlack,
-    }
+    painter.paint(&text, Style::default().bold())
 }
 
-fn print_location<W: io::Write>(
+/// Writes the location line of a frame. `path` is the absolute path of `file`, which hyperlinks
+/// point to.
+pub(super) fn print_location<W: io::Write>(
     sink: &mut W,
+    painter: &Painter,
     file: Option<&str>,
+    path: Option<&str>,
     line: Option<u32>,
+    column: Option<u32>,
     module_path: Option<&str>,
 ) -> io::Result<()> {
     if let Some(file) = file {
@@ -289,8 +370,19 @@ fn print_location<W: io::Write>(
         let mut loc = file.to_string();
         if let Some(line) = line {
             let _ = write!(loc, ":{line}");
+            if let Some(column) = column {
+                let _ = write!(loc, ":{column}");
+            }
         }
-        writeln!(sink, "{}", format!("└─ {mod_path} @ {loc}").dimmed())?;
+        let dimmed = Style::default().dimmed();
+        writeln!(
+            sink,
+            "{}{}{}{}",
+            painter.paint("└─ ", dimmed),
+            painter.module(mod_path, dimmed),
+            painter.paint(" @ ", dimmed),
+            hyperlink(painter, painter.paint(&loc, dimmed), path, line, column)
+        )?;
     }
 
     Ok(())
@@ -0,0 +1,191 @@
+//! Decoding captured data on multiple threads.
+
+use std::{collections::VecDeque, num::NonZeroUsize, thread};
+
+use crate::{
+    stream::{self, Unframe},
+    DecodeError, Encoding, Frame, Table,
+};
+
+/// How much data each thread decodes at a time.
+const CHUNK_SIZE: usize = if cfg!(test) { 1 << 10 } else { 1 << 20 };
+
+/// An iterator over the frames in captured data, returned by [`Table::decode_parallel`].
+pub struct ParallelFrames<'t, 'd> {
+    table: &'t Table,
+    data: &'d [u8],
+    threads: NonZeroUsize,
+    /// The decoded frames that have not been returned yet, in order
+    decoded: VecDeque<Result<Frame<'t>, DecodeError>>,
+    /// Used for encodings whose frames can't be found without decoding the previous ones
+    sequential: Option<Box<dyn Unframe>>,
+    /// Whether a frame could not be decoded, and the encoding can't recover from that
+    failed: bool,
+}
+
+impl<'t, 'd> ParallelFrames<'t, 'd> {
+    pub(crate) fn new(table: &'t Table, data: &'d [u8]) -> Self {
+        let sequential = match table.encoding() {
+            // frames are delimited by `0x00` and independent of each other
+            Encoding::Rzcobs | Encoding::RzcobsCrc => None,
+            Encoding::Raw | Encoding::Lzss => {
+                let mut unframe = stream::unframe(table.encoding());
+                unframe.received(data);
+                Some(unframe)
+            }
+        };
+
+        Self {
+            table,
+            data,
+            threads: thread::available_parallelism().unwrap_or(NonZeroUsize::MIN),
+            decoded: VecDeque::new(),
+            sequential,
+            failed: false,
+        }
+    }
+
+    /// Sets the number of threads to decode on, instead of the available parallelism.
+    pub fn threads(mut self, threads: NonZeroUsize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Splits off the next chunk of `data`, ending after a frame separator.
+    fn next_chunk(&mut self) -> &'d [u8] {
+        let end = match self.data.get(CHUNK_SIZE..) {
+            Some(rest) => match rest.iter().position(|&x| x == 0) {
+                Some(zero) => CHUNK_SIZE + zero + 1,
+                None => self.data.len(),
+            },
+            None => self.data.len(),
+        };
+        let (chunk, rest) = self.data.split_at(end);
+        self.data = rest;
+        chunk
+    }
+
+    /// Decodes the next chunk on each thread.
+    fn decode_batch(&mut self) {
+        let chunks = (0..self.threads.get())
+            .map(|_| self.next_chunk())
+            .filter(|chunk| !chunk.is_empty())
+            .collect::<Vec<_>>();
+
+        let table = self.table;
+        thread::scope(|scope| {
+            let threads = chunks
+                .into_iter()
+                .map(|chunk| scope.spawn(move || decode_chunk(table, chunk)))
+                .collect::<Vec<_>>();
+            for thread in threads {
+                self.decoded.extend(thread.join().unwrap());
+            }
+        });
+    }
+}
+
+fn decode_chunk<'t>(table: &'t Table, chunk: &[u8]) -> Vec<Result<Frame<'t>, DecodeError>> {
+    let mut unframe = stream::unframe(table.encoding());
+    unframe.received(chunk);
+
+    let mut frames = Vec::new();
+    loop {
+        match unframe.decode(table) {
+            // an incomplete frame at the end of the data is ignored
+            Err(DecodeError::UnexpectedEof) => return frames,
+            frame => frames.push(frame),
+        }
+    }
+}
+
+impl<'t> Iterator for ParallelFrames<'t, '_> {
+    type Item = Result<Frame<'t>, DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(unframe) = &mut self.sequential {
+            if self.failed {
+                return None;
+            }
+            return match unframe.decode(self.table) {
+                Err(DecodeError::UnexpectedEof) => None,
+                Err(e) => {
+                    // like `Frames`, stop once the data can't be decoded any further
+                    self.failed = !self.table.encoding().can_recover();
+                    Some(Err(e))
+                }
+                frame => Some(frame),
+            };
+        }
+
+        while self.decoded.is_empty() && !self.data.is_empty() {
+            self.decode_batch();
+        }
+        self.decoded.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Tag;
+
+    #[test]
+    fn same_as_sequential() {
+        let table = Table::with_entries(&[(1, Tag::Info, "x={=u8}")], Encoding::Rzcobs);
+
+        // enough frames for several chunks, with some malformed ones and an incomplete one
+        let mut data = vec![0];
+        for i in 0..3 * CHUNK_SIZE / 4 {
+            match i % 1000 {
+                999 => data.extend([0x80, 0]),
+                _ => data.extend([1, (i % 255 + 1) as u8, 0x7a, 0]),
+            }
+        }
+        data.extend([1, 42]);
+
+        let mut decoder = table.new_stream_decoder();
+        decoder.received(&data);
+        let mut expected = Vec::new();
+        loop {
+            match decoder.decode() {
+                Ok(frame) => expected.push(Ok(frame.display_message().to_string())),
+                Err(DecodeError::UnexpectedEof) => break,
+                Err(e) => expected.push(Err(e)),
+            }
+        }
+
+        for threads in [1, 3] {
+            let frames = table
+                .decode_parallel(&data)
+                .threads(NonZeroUsize::new(threads).unwrap())
+                .map(|frame| frame.map(|frame| frame.display_message().to_string()))
+                .collect::<Vec<_>>();
+            assert_eq!(frames, expected);
+        }
+    }
+
+    #[test]
+    fn stops_after_unrecoverable_error() {
+        let table = Table::with_entries(&[(1, Tag::Info, "x={=u8}")], Encoding::Raw);
+        // a frame with an unknown index, followed by data that would decode
+        let data = [1, 0, 42, 9, 0, 43, 1, 0, 44];
+
+        let frames = table
+            .decode_parallel(&data)
+            .map(|frame| frame.map(|frame| frame.display_message().to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(frames, [Ok("x=42".to_owned()), Err(DecodeError::Malformed)]);
+
+        // the same as `Table::frames`
+        let frames = std::sync::Arc::new(table)
+            .frames(&data[..])
+            .map(|frame| {
+                frame
+                    .unwrap()
+                    .map(|frame| frame.frame().display_message().to_string())
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(frames, [Ok("x=42".to_owned()), Err(DecodeError::Malformed)]);
+    }
+}
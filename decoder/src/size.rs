@@ -0,0 +1,129 @@
+use std::fmt;
+
+use defmt_parser::{Fragment, ParserMode, Type};
+
+use crate::{decoder::prepare_params, IntegerEncoding};
+
+/// Worst case of the encoded size of the arguments of a format string, see [`Table::args_size`].
+///
+/// [`Table::args_size`]: crate::Table::args_size
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ArgsSize {
+    /// Size in bytes of the arguments whose size is bounded, plus the length prefixes of the others
+    pub bounded: usize,
+    /// Whether an argument can be arbitrarily large, like a `str` or a value formatted with `{}`
+    pub unbounded: bool,
+}
+
+impl fmt::Display for ArgsSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.unbounded {
+            false => write!(f, "{}", self.bounded),
+            true => write!(f, "{}+", self.bounded),
+        }
+    }
+}
+
+impl std::ops::Add for ArgsSize {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            bounded: self.bounded + other.bounded,
+            unbounded: self.unbounded || other.unbounded,
+        }
+    }
+}
+
+pub(crate) fn args_size(format: &str, integer_encoding: IntegerEncoding) -> Option<ArgsSize> {
+    let mut params = defmt_parser::parse(format, ParserMode::ForwardsCompatible)
+        .ok()?
+        .into_iter()
+        .filter_map(|frag| match frag {
+            Fragment::Parameter(param) => Some(param),
+            Fragment::Literal(_) => None,
+        })
+        .collect::<Vec<_>>();
+    prepare_params(&mut params);
+
+    let integer = |bits: usize| match integer_encoding {
+        IntegerEncoding::Fixed => bits / 8,
+        // 7 bits per byte
+        IntegerEncoding::Varint => bits.div_ceil(7),
+    };
+    let bounded = |bounded| ArgsSize {
+        bounded,
+        unbounded: false,
+    };
+    let unbounded = |bounded| ArgsSize {
+        bounded,
+        unbounded: true,
+    };
+
+    let size = params
+        .iter()
+        .map(|param| match &param.ty {
+            Type::U8 | Type::I8 | Type::Bool => bounded(1),
+            Type::U16 | Type::I16 => bounded(integer(16)),
+            Type::U32 | Type::I32 | Type::Usize | Type::Isize => bounded(integer(32)),
+            Type::U64 | Type::I64 => bounded(integer(64)),
+            Type::U128 | Type::I128 => bounded(integer(128)),
+            Type::F32 | Type::Char => bounded(4),
+            Type::F64 => bounded(8),
+            Type::BitField(range) => {
+                let lowest_byte = range.start / 8;
+                let highest_byte = (range.end - 1) / 8;
+                bounded(match highest_byte - lowest_byte + 1 {
+                    1 => 1,
+                    2 => integer(16),
+                    3..=4 => integer(32),
+                    5..=8 => integer(64),
+                    _ => integer(128),
+                })
+            }
+            Type::IStr => bounded(integer(16)),
+            Type::U8Array(len) => bounded(*len),
+            // length prefix
+            Type::Str | Type::U8Slice | Type::FormatSlice => unbounded(integer(32)),
+            // index of the format string
+            Type::Format => unbounded(integer(16)),
+            Type::FormatArray(_) => unbounded(0),
+            // terminator
+            Type::Debug | Type::Display => unbounded(1),
+            Type::FormatSequence => unbounded(integer(16)),
+        })
+        .fold(ArgsSize::default(), |sum, size| sum + size);
+
+    Some(size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixed() {
+        let size = |format| {
+            args_size(format, IntegerEncoding::Fixed)
+                .unwrap()
+                .to_string()
+        };
+
+        assert_eq!(size("hello"), "0");
+        assert_eq!(size("{=u8} {=u32} {=f64} {0=u8}"), "13");
+        assert_eq!(size("{=0..4} {0=12..16} {=istr}"), "4");
+        assert_eq!(size("{=[u8; 3]} {=str}"), "7+");
+        assert_eq!(size("{}"), "2+");
+    }
+
+    #[test]
+    fn varint() {
+        let size = |format| {
+            args_size(format, IntegerEncoding::Varint)
+                .unwrap()
+                .to_string()
+        };
+
+        assert_eq!(size("{=u16} {=u32} {=u64} {=u128}"), "37");
+    }
+}
@@ -0,0 +1,99 @@
+/// Received data that has not been decoded yet.
+///
+/// Decoded data is consumed from the front by moving a start offset instead of shifting the
+/// remaining data, which would be quadratic in the amount of buffered data. The consumed space is
+/// reclaimed once it makes up at least half of the buffer, when more data is received.
+#[derive(Default)]
+pub(super) struct Buffer {
+    data: Vec<u8>,
+    start: usize,
+    /// Number of bytes from `start` on that are known not to contain a `0x00`
+    scanned: usize,
+}
+
+impl Buffer {
+    pub(super) fn new() -> Self {
+        Self::default()
+    }
+
+    pub(super) fn extend(&mut self, data: &[u8]) {
+        if self.start != 0 && self.start >= self.data.len() - self.start {
+            self.data.drain(..self.start);
+            self.start = 0;
+        }
+        self.data.extend_from_slice(data);
+    }
+
+    /// The data that has not been consumed yet.
+    pub(super) fn as_slice(&self) -> &[u8] {
+        &self.data[self.start..]
+    }
+
+    pub(super) fn is_empty(&self) -> bool {
+        self.start == self.data.len()
+    }
+
+    /// Returns the position of the first `0x00` in the data that has not been consumed yet.
+    ///
+    /// Data that was searched before is not searched again, so that searching for the end of a
+    /// frame that is received in many small parts is not quadratic.
+    pub(super) fn find_zero(&mut self) -> Option<usize> {
+        let unscanned = &self.as_slice()[self.scanned..];
+        match unscanned.iter().position(|&x| x == 0) {
+            Some(position) => Some(self.scanned + position),
+            None => {
+                self.scanned += unscanned.len();
+                None
+            }
+        }
+    }
+
+    pub(super) fn consume(&mut self, n: usize) {
+        assert!(n <= self.data.len() - self.start);
+        self.start += n;
+        self.scanned = self.scanned.saturating_sub(n);
+        if self.is_empty() {
+            self.data.clear();
+            self.start = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn consume_and_reclaim() {
+        let mut buffer = Buffer::new();
+        buffer.extend(&[1, 2, 3, 4]);
+        buffer.consume(1);
+        assert_eq!(buffer.as_slice(), [2, 3, 4]);
+
+        // less than half consumed, nothing is moved
+        buffer.extend(&[5]);
+        assert_eq!((buffer.start, buffer.as_slice()), (1, &[2, 3, 4, 5][..]));
+
+        buffer.consume(2);
+        buffer.extend(&[6]);
+        assert_eq!((buffer.start, buffer.as_slice()), (0, &[4, 5, 6][..]));
+
+        buffer.consume(3);
+        assert!(buffer.is_empty());
+        assert_eq!(buffer.data.len(), 0);
+    }
+
+    #[test]
+    fn find_zero() {
+        let mut buffer = Buffer::new();
+        buffer.extend(&[1, 2]);
+        assert_eq!(buffer.find_zero(), None);
+        assert_eq!(buffer.scanned, 2);
+
+        buffer.extend(&[3, 0, 4]);
+        assert_eq!(buffer.find_zero(), Some(3));
+        buffer.consume(4);
+        assert_eq!(buffer.find_zero(), None);
+        assert_eq!(buffer.scanned, 1);
+    }
+}
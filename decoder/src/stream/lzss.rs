@@ -0,0 +1,156 @@
+use super::{
+    buffer::Buffer,
+    rzcobs::{pop_frame, received},
+    Unframe,
+};
+use crate::{DecodeError, Table};
+
+/// Size of the history the encoder looks for matches in.
+const WINDOW_SIZE: usize = 256;
+const MIN_MATCH: usize = 3;
+const RESET_FLAG: u8 = 0x80;
+const SEQUENCE_MASK: u8 = 0x7f;
+
+pub(crate) struct Lzss {
+    raw: Buffer,
+    /// The compressed frame
+    compressed: Vec<u8>,
+    /// The uncompressed data of the previous frames, at least the last `WINDOW_SIZE` bytes
+    history: Vec<u8>,
+    /// Sequence number of the next frame; `None` until a frame resetting the history is received
+    next_sequence: Option<u8>,
+    /// The decompressed frame
+    frame: Vec<u8>,
+}
+
+impl Lzss {
+    pub(crate) fn new() -> Self {
+        Self {
+            raw: Buffer::new(),
+            compressed: Vec::new(),
+            history: Vec::new(),
+            next_sequence: None,
+            frame: Vec::new(),
+        }
+    }
+
+    /// Decompresses a frame, appending it to the history.
+    fn decompress(&mut self, compressed: &[u8]) -> Result<Vec<u8>, DecodeError> {
+        let (&header, mut data) = compressed.split_first().ok_or(DecodeError::Malformed)?;
+        let sequence = header & SEQUENCE_MASK;
+        if header & RESET_FLAG != 0 {
+            self.history.clear();
+        } else if self.next_sequence != Some(sequence) {
+            // a frame was lost, or we started in the middle of the stream; wait for the next reset
+            self.next_sequence = None;
+            return Err(DecodeError::Malformed);
+        }
+        // until the frame is decompressed successfully
+        self.next_sequence = None;
+
+        let start = self.history.len();
+        while let Some((&flags, rest)) = data.split_first() {
+            data = rest;
+            for item in 0..8 {
+                if data.is_empty() {
+                    break;
+                }
+
+                if flags & (1 << item) == 0 {
+                    self.history.push(data[0]);
+                    data = &data[1..];
+                } else {
+                    let (distance, len) = match data {
+                        [distance, len, ..] => (*distance as usize + 1, *len as usize + MIN_MATCH),
+                        _ => return Err(DecodeError::Malformed),
+                    };
+                    if distance > self.history.len() {
+                        return Err(DecodeError::Malformed);
+                    }
+                    // byte by byte, because the match may overlap the bytes it produces
+                    for _ in 0..len {
+                        let byte = self.history[self.history.len() - distance];
+                        self.history.push(byte);
+                    }
+                    data = &data[2..];
+                }
+            }
+        }
+        let frame = self.history[start..].to_vec();
+
+        if self.history.len() > WINDOW_SIZE {
+            self.history.drain(..self.history.len() - WINDOW_SIZE);
+        }
+        self.next_sequence = Some((sequence + 1) & SEQUENCE_MASK);
+
+        Ok(frame)
+    }
+}
+
+impl Unframe for Lzss {
+    fn received(&mut self, data: &[u8]) {
+        received(&mut self.raw, data)
+    }
+
+    fn next_frame(&mut self, _table: &Table) -> Result<&[u8], DecodeError> {
+        pop_frame(&mut self.raw, &mut self.compressed)?;
+        // moved out for the duration of `decompress`, which borrows all of `self`
+        let compressed = std::mem::take(&mut self.compressed);
+        let frame = self.decompress(&compressed);
+        self.compressed = compressed;
+        self.frame = frame?;
+        Ok(&self.frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decompress() {
+        let mut lzss = Lzss::new();
+
+        // 3 literals and an overlapping match of 6 bytes at distance 3
+        let frame = [0x80, 0b1000, b'a', b'b', b'c', 2, 3];
+        assert_eq!(lzss.decompress(&frame).unwrap(), b"abcabcabc");
+
+        // a match of 5 bytes into the previous frame
+        let frame = [0x01, 0b1, 2, 2];
+        assert_eq!(lzss.decompress(&frame).unwrap(), b"abcab");
+    }
+
+    #[test]
+    fn wait_for_reset() {
+        let mut lzss = Lzss::new();
+
+        // started in the middle of the stream
+        assert_eq!(
+            lzss.decompress(&[0x05, 0, b'a']),
+            Err(DecodeError::Malformed)
+        );
+        assert_eq!(lzss.decompress(&[0x86, 0, b'a']).unwrap(), b"a");
+        assert_eq!(lzss.decompress(&[0x07, 0b1, 0, 0]).unwrap(), b"aaa");
+
+        // lost frame 8
+        assert_eq!(
+            lzss.decompress(&[0x09, 0b1, 0, 0]),
+            Err(DecodeError::Malformed)
+        );
+        assert_eq!(
+            lzss.decompress(&[0x0a, 0b1, 0, 0]),
+            Err(DecodeError::Malformed)
+        );
+        assert_eq!(lzss.decompress(&[0x8b, 0, b'b']).unwrap(), b"b");
+    }
+
+    #[test]
+    fn match_before_history() {
+        let mut lzss = Lzss::new();
+
+        assert_eq!(
+            lzss.decompress(&[0x80, 0b10, b'a', 1, 0]),
+            Err(DecodeError::Malformed)
+        );
+    }
+}
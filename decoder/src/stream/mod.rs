@@ -1,10 +1,15 @@
+mod buffer;
+mod lzss;
 mod raw;
 mod rzcobs;
+mod rzcobs_crc;
 
-pub use raw::Raw;
-pub use rzcobs::Rzcobs;
+pub(crate) use lzss::Lzss;
+pub(crate) use raw::Raw;
+pub(crate) use rzcobs::Rzcobs;
+pub(crate) use rzcobs_crc::RzcobsCrc;
 
-use crate::{DecodeError, Frame};
+use crate::{DecodeError, Encoding, Frame, Table};
 
 pub trait StreamDecoder {
     /// Push received data to the decoder. The decoder stores it
@@ -13,3 +18,81 @@ pub trait StreamDecoder {
 
     fn decode(&mut self) -> Result<Frame<'_>, DecodeError>;
 }
+
+/// Splits the received data into frames, without decoding them.
+///
+/// This is the part of a [`StreamDecoder`] that depends on the encoding, kept separate from the
+/// [`Table`] so that it can be owned together with a shared table, as in [`crate::Frames`].
+pub(crate) trait Unframe: Send {
+    fn received(&mut self, data: &[u8]);
+
+    /// Returns the data of the next frame, ready to be decoded by the `table`.
+    ///
+    /// Returns `UnexpectedEof` if more data needs to be received first.
+    fn next_frame(&mut self, table: &Table) -> Result<&[u8], DecodeError>;
+
+    /// Decodes the next frame, skipping frames that identify the image.
+    fn decode<'t>(&mut self, table: &'t Table) -> Result<Frame<'t>, DecodeError> {
+        loop {
+            let frame = self.next_frame(table)?;
+            if let Ok(Some(_)) = image_id(table, frame) {
+                continue;
+            }
+            return match table.decode(frame) {
+                Ok((frame, _consumed)) => Ok(frame),
+                // the frame is complete, so missing data means it is corrupted
+                Err(DecodeError::UnexpectedEof) => Err(DecodeError::Malformed),
+                Err(e) => Err(e),
+            };
+        }
+    }
+}
+
+/// Index of the frame the firmware writes to identify its image, with the `image-id` feature
+const IMAGE_ID_INDEX: u128 = 0xffff;
+
+/// Returns the image ID and the size of the frame, if `data` starts with a frame that identifies
+/// the image.
+///
+/// The ID is the address of the `_defmt_image_id` function in the image, see [`Table::image_id`].
+pub(crate) fn image_id(table: &Table, data: &[u8]) -> Result<Option<(u32, usize)>, DecodeError> {
+    let mut decoder = crate::decoder::Decoder::new(table, data);
+    if decoder.read_unsigned(16)? != IMAGE_ID_INDEX {
+        return Ok(None);
+    }
+    let id = decoder.read_unsigned(32)? as u32;
+    Ok(Some((id, data.len() - decoder.bytes.len())))
+}
+
+pub(crate) fn unframe(encoding: Encoding) -> Box<dyn Unframe> {
+    match encoding {
+        Encoding::Raw => Box::new(Raw::new()),
+        Encoding::Rzcobs => Box::new(Rzcobs::new()),
+        Encoding::RzcobsCrc => Box::new(RzcobsCrc::new()),
+        Encoding::Lzss => Box::new(Lzss::new()),
+    }
+}
+
+pub(crate) struct Decoder<'t> {
+    table: &'t Table,
+    unframe: Box<dyn Unframe>,
+}
+
+impl<'t> Decoder<'t> {
+    pub(crate) fn new(table: &'t Table) -> Self {
+        Self {
+            table,
+            unframe: unframe(table.encoding()),
+        }
+    }
+}
+
+impl StreamDecoder for Decoder<'_> {
+    fn received(&mut self, data: &[u8]) {
+        self.unframe.received(data)
+    }
+
+    fn decode(&mut self) -> Result<Frame<'_>, DecodeError> {
+        self.unframe.decode(self.table)
+    }
+}
@@ -1,32 +1,45 @@
-use super::StreamDecoder;
+use super::{buffer::Buffer, image_id, Unframe};
 use crate::{DecodeError, Frame, Table};
 
-pub struct Raw<'a> {
-    table: &'a Table,
-    data: Vec<u8>,
+pub(crate) struct Raw {
+    data: Buffer,
+    frame: Vec<u8>,
 }
 
-impl<'a> Raw<'a> {
-    pub fn new(table: &'a Table) -> Self {
+impl Raw {
+    pub(crate) fn new() -> Self {
         Self {
-            table,
-            data: Vec::new(),
+            data: Buffer::new(),
+            frame: Vec::new(),
         }
     }
 }
 
-impl<'a> StreamDecoder for Raw<'a> {
+impl Unframe for Raw {
     fn received(&mut self, data: &[u8]) {
-        self.data.extend_from_slice(data);
+        self.data.extend(data);
     }
 
-    fn decode(&mut self) -> Result<Frame<'_>, DecodeError> {
-        match self.table.decode(&self.data) {
-            Ok((frame, consumed)) => {
-                self.data.drain(0..consumed);
-                Ok(frame)
-            }
-            Err(e) => Err(e),
+    /// Frames are not delimited, so they have to be decoded to find where they end.
+    fn next_frame(&mut self, table: &Table) -> Result<&[u8], DecodeError> {
+        let consumed = match image_id(table, self.data.as_slice())? {
+            Some((_id, consumed)) => consumed,
+            None => table.decode(self.data.as_slice())?.1,
+        };
+        self.frame.clear();
+        self.frame
+            .extend_from_slice(&self.data.as_slice()[..consumed]);
+        self.data.consume(consumed);
+        Ok(&self.frame)
+    }
+
+    /// Decodes the frame only once, unlike the default implementation.
+    fn decode<'t>(&mut self, table: &'t Table) -> Result<Frame<'t>, DecodeError> {
+        while let Some((_id, consumed)) = image_id(table, self.data.as_slice())? {
+            self.data.consume(consumed);
         }
+        let (frame, consumed) = table.decode(self.data.as_slice())?;
+        self.data.consume(consumed);
+        Ok(frame)
     }
 }
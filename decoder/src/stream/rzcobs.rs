@@ -1,12 +1,12 @@
-use super::StreamDecoder;
-use crate::{DecodeError, Frame, Table};
+use super::{buffer::Buffer, Unframe};
+use crate::{DecodeError, Table};
 
-/// Decode a full message.
+/// Decode a full message into `res`, replacing its contents.
 ///
 /// `data` must be a full rzCOBS encoded message. Decoding partial
 /// messages is not possible. `data` must NOT include any `0x00` separator byte.
-fn rzcobs_decode(data: &[u8]) -> Result<Vec<u8>, DecodeError> {
-    let mut res = vec![];
+fn rzcobs_decode(data: &[u8], res: &mut Vec<u8>) -> Result<(), DecodeError> {
+    res.clear();
     let mut data = data.iter().rev().cloned();
     while let Some(x) = data.next() {
         match x {
@@ -36,60 +36,99 @@ fn rzcobs_decode(data: &[u8]) -> Result<Vec<u8>, DecodeError> {
     }
 
     res.reverse();
-    Ok(res)
+    Ok(())
 }
 
-pub struct Rzcobs<'a> {
-    table: &'a Table,
-    raw: Vec<u8>,
+/// Removes the first frame from `raw` and decodes it into `frame`.
+///
+/// `raw` must not start with `0x00` separator bytes.
+pub(super) fn pop_frame(raw: &mut Buffer, frame: &mut Vec<u8>) -> Result<(), DecodeError> {
+    // Find frame separator. If not found, we don't have enough data yet.
+    let zero = raw.find_zero().ok_or(DecodeError::UnexpectedEof)?;
+    let data = raw.as_slice();
+
+    let res = rzcobs_decode(&data[..zero], frame);
+
+    // Even if it failed, pop the data off so we don't get stuck.
+    // Pop off the frame + 1 or more separator zero-bytes
+    let separators = data[zero..]
+        .iter()
+        .position(|&x| x != 0)
+        .unwrap_or(data.len() - zero);
+    raw.consume(zero + separators);
+
+    res
 }
 
-impl<'a> Rzcobs<'a> {
-    pub fn new(table: &'a Table) -> Self {
-        Self {
-            table,
-            raw: Vec::new(),
+/// Trims zeros from the left of `data` if nothing is stored in `raw`, so that `raw` starts at
+/// the first non-zero byte.
+pub(super) fn received(raw: &mut Buffer, mut data: &[u8]) {
+    if raw.is_empty() {
+        while data.first() == Some(&0) {
+            data = &data[1..]
         }
     }
+
+    raw.extend(data);
 }
 
-impl<'a> StreamDecoder for Rzcobs<'a> {
-    fn received(&mut self, mut data: &[u8]) {
-        // Trim zeros from the left, start storing at first non-zero byte.
-        if self.raw.is_empty() {
-            while data.first() == Some(&0) {
-                data = &data[1..]
-            }
+pub(crate) struct Rzcobs {
+    raw: Buffer,
+    /// The decoded frame, reused to not allocate for every frame
+    frame: Vec<u8>,
+}
+
+impl Rzcobs {
+    pub(crate) fn new() -> Self {
+        Self {
+            raw: Buffer::new(),
+            frame: Vec::new(),
         }
+    }
+}
 
-        self.raw.extend_from_slice(data);
+impl Unframe for Rzcobs {
+    fn received(&mut self, data: &[u8]) {
+        received(&mut self.raw, data)
     }
 
-    fn decode(&mut self) -> Result<Frame<'_>, DecodeError> {
-        // Find frame separator. If not found, we don't have enough data yet.
-        let zero = self
-            .raw
-            .iter()
-            .position(|&x| x == 0)
-            .ok_or(DecodeError::UnexpectedEof)?;
-
-        let frame = rzcobs_decode(&self.raw[..zero]);
-
-        // Even if it failed, pop the data off so we don't get stuck.
-        // Pop off the frame + 1 or more separator zero-bytes
-        if let Some(nonzero) = self.raw[zero..].iter().position(|&x| x != 0) {
-            self.raw.drain(0..zero + nonzero);
-        } else {
-            self.raw.clear();
-        }
+    fn next_frame(&mut self, _table: &Table) -> Result<&[u8], DecodeError> {
+        pop_frame(&mut self.raw, &mut self.frame)?;
+        Ok(&self.frame)
+    }
+}
 
-        assert!(self.raw.is_empty() || self.raw[0] != 0);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{stream::Decoder, Encoding, StreamDecoder, Tag};
 
-        let frame: Vec<u8> = frame?;
-        match self.table.decode(&frame) {
-            Ok((frame, _consumed)) => Ok(frame),
-            Err(DecodeError::UnexpectedEof) => Err(DecodeError::Malformed),
-            Err(DecodeError::Malformed) => Err(DecodeError::Malformed),
-        }
+    fn decode_message(decoder: &mut Decoder) -> Result<String, DecodeError> {
+        decoder
+            .decode()
+            .map(|frame| frame.display_message().to_string())
+    }
+
+    #[test]
+    fn decode() {
+        let table = Table::with_entries(&[(1, Tag::Info, "x={=u8}")], Encoding::Rzcobs);
+        let mut decoder = Decoder::new(&table);
+
+        // leading separators, 2 frames and the start of a third one
+        decoder.received(&[0, 0, 1, 42, 0x7a, 0, 1, 43, 0x7a, 0, 1]);
+        assert_eq!(decode_message(&mut decoder).unwrap(), "x=42");
+        assert_eq!(decode_message(&mut decoder).unwrap(), "x=43");
+        assert_eq!(
+            decode_message(&mut decoder),
+            Err(DecodeError::UnexpectedEof)
+        );
+
+        decoder.received(&[44, 0x7a, 0, 0]);
+        assert_eq!(decode_message(&mut decoder).unwrap(), "x=44");
+
+        // a malformed frame is skipped
+        decoder.received(&[0x80, 0, 1, 45, 0x7a, 0]);
+        assert_eq!(decode_message(&mut decoder), Err(DecodeError::Malformed));
+        assert_eq!(decode_message(&mut decoder).unwrap(), "x=45");
     }
 }
@@ -0,0 +1,79 @@
+use super::{
+    buffer::Buffer,
+    rzcobs::{pop_frame, received},
+    Unframe,
+};
+use crate::{DecodeError, Table};
+
+const POLYNOMIAL: u16 = 0x1021;
+const INITIAL: u16 = 0xffff;
+
+/// CRC-16/IBM-3740, as computed by the target.
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc = INITIAL;
+    for &byte in data {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ POLYNOMIAL
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// Splits off the CRC at the end of `frame` and checks it.
+fn check_crc(frame: &[u8]) -> Result<&[u8], DecodeError> {
+    if frame.len() < 2 {
+        return Err(DecodeError::Malformed);
+    }
+    let (data, crc) = frame.split_at(frame.len() - 2);
+    if crc16(data) != u16::from_le_bytes([crc[0], crc[1]]) {
+        return Err(DecodeError::ChecksumMismatch);
+    }
+    Ok(data)
+}
+
+pub(crate) struct RzcobsCrc {
+    raw: Buffer,
+    frame: Vec<u8>,
+}
+
+impl RzcobsCrc {
+    pub(crate) fn new() -> Self {
+        Self {
+            raw: Buffer::new(),
+            frame: Vec::new(),
+        }
+    }
+}
+
+impl Unframe for RzcobsCrc {
+    fn received(&mut self, data: &[u8]) {
+        received(&mut self.raw, data)
+    }
+
+    fn next_frame(&mut self, _table: &Table) -> Result<&[u8], DecodeError> {
+        pop_frame(&mut self.raw, &mut self.frame)?;
+        check_crc(&self.frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc() {
+        assert_eq!(crc16(b"123456789"), 0x29b1);
+
+        assert_eq!(check_crc(b"123456789\xb1\x29"), Ok(&b"123456789"[..]));
+        assert_eq!(
+            check_crc(b"123456780\xb1\x29"),
+            Err(DecodeError::ChecksumMismatch)
+        );
+        assert_eq!(check_crc(b"\xb1"), Err(DecodeError::Malformed));
+    }
+}
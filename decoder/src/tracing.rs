@@ -0,0 +1,261 @@
+//! This module provides interoperability utilities between [`defmt`] and the [`tracing`]
+//! ecosystem.
+//!
+//! Decoded frames are emitted as `tracing` events, so they reach the same subscribers as the events
+//! of the host.
+//!
+//! [`tracing`]: https://crates.io/crates/tracing
+//! [`defmt`]: https://crates.io/crates/defmt
+
+use tracing_core::{
+    callsite::{self, Callsite as _, Identifier},
+    dispatcher,
+    field::{self, FieldSet, Value},
+    metadata::Kind,
+    subscriber::Interest,
+    Event, Level, LevelFilter, Metadata,
+};
+
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        Mutex, OnceLock,
+    },
+};
+
+use crate::{Frame, Location};
+
+/// Target of events whose location is not known.
+const DEFAULT_TARGET: &str = "defmt";
+const FIELD_NAMES: &[&str] = &["message", "timestamp", "index"];
+
+/// Emits a defmt frame as a `tracing` event to the current subscriber.
+///
+/// The event has the level of the frame, and frames without a level (from `defmt::println!`)
+/// are emitted at the `INFO` level. Its target is the module path of `location`, or `defmt` if the
+/// location is not known. The message is in the `message` field, the formatted timestamp (if the
+/// firmware has one) in the `timestamp` field and the index of the format string in the interned
+/// string table in the `index` field.
+pub fn emit_frame(frame: &Frame<'_>, location: Option<&Location>) {
+    let level = match frame.level() {
+        Some(level) => to_tracing_level(level),
+        None => Level::INFO,
+    };
+    if level > LevelFilter::current() {
+        return;
+    }
+
+    let callsite = DefmtCallsite::get(frame.index(), level, location);
+    if callsite.interest().is_never() {
+        return;
+    }
+    let metadata = callsite.metadata();
+
+    let message = field::display(frame.display_message());
+    let timestamp = frame.display_timestamp().map(field::display);
+    let index = frame.index();
+    let fields = metadata.fields();
+    let values = [
+        (
+            &fields.field("message").unwrap(),
+            Some(&message as &dyn Value),
+        ),
+        (
+            &fields.field("timestamp").unwrap(),
+            timestamp.as_ref().map(|ts| ts as &dyn Value),
+        ),
+        (&fields.field("index").unwrap(), Some(&index as &dyn Value)),
+    ];
+
+    dispatcher::get_default(|dispatch| {
+        if dispatch.enabled(metadata) {
+            dispatch.event(&Event::new(metadata, &fields.value_set(&values)));
+        }
+    });
+}
+
+fn to_tracing_level(level: crate::Level) -> Level {
+    match level {
+        crate::Level::Trace => Level::TRACE,
+        crate::Level::Debug => Level::DEBUG,
+        crate::Level::Info => Level::INFO,
+        crate::Level::Warn => Level::WARN,
+        crate::Level::Error => Level::ERROR,
+    }
+}
+
+/// The callsite of the events emitted for one log statement of the firmware.
+///
+/// `tracing` requires callsites and their metadata to be `'static`, but the log statements are only
+/// known at runtime. Callsites are created and leaked the first time a log statement emits an
+/// event, so the memory used is bounded by the number of log statements.
+struct DefmtCallsite {
+    metadata: OnceLock<Metadata<'static>>,
+    interest: AtomicU8,
+}
+
+const INTEREST_NEVER: u8 = 0;
+const INTEREST_SOMETIMES: u8 = 1;
+const INTEREST_ALWAYS: u8 = 2;
+
+/// The callsites, by index of the format string
+static CALLSITES: Mutex<BTreeMap<u64, Vec<&'static DefmtCallsite>>> = Mutex::new(BTreeMap::new());
+
+impl DefmtCallsite {
+    fn get(index: u64, level: Level, location: Option<&Location>) -> &'static Self {
+        let target = location.map_or(DEFAULT_TARGET, |location| &*location.module);
+        let file = location.map(|location| location.file.display().to_string());
+        let line = location.map(|location| location.line as u32);
+
+        let mut callsites = CALLSITES.lock().unwrap();
+        let callsites = callsites.entry(index).or_default();
+
+        let existing = callsites.iter().find(|callsite| {
+            let metadata = callsite.metadata();
+            *metadata.level() == level
+                && metadata.target() == target
+                && metadata.file() == file.as_deref()
+                && metadata.line() == line
+        });
+        if let Some(callsite) = existing {
+            return callsite;
+        }
+
+        let callsite: &'static Self = Box::leak(Box::new(Self {
+            metadata: OnceLock::new(),
+            interest: AtomicU8::new(INTEREST_SOMETIMES),
+        }));
+        let target: &'static str = Box::leak(target.into());
+        let metadata = Metadata::new(
+            "defmt frame",
+            target,
+            level,
+            file.map(|file| &*Box::leak(file.into_boxed_str())),
+            line,
+            location.map(|_| target),
+            FieldSet::new(FIELD_NAMES, Identifier(callsite)),
+            Kind::EVENT,
+        );
+        callsite.metadata.set(metadata).ok();
+        callsite::register(callsite);
+
+        callsites.push(callsite);
+        callsite
+    }
+
+    fn interest(&self) -> Interest {
+        match self.interest.load(Ordering::Relaxed) {
+            INTEREST_NEVER => Interest::never(),
+            INTEREST_ALWAYS => Interest::always(),
+            _ => Interest::sometimes(),
+        }
+    }
+}
+
+impl callsite::Callsite for DefmtCallsite {
+    fn set_interest(&self, interest: Interest) {
+        let interest = match () {
+            _ if interest.is_never() => INTEREST_NEVER,
+            _ if interest.is_always() => INTEREST_ALWAYS,
+            _ => INTEREST_SOMETIMES,
+        };
+        self.interest.store(interest, Ordering::Relaxed);
+    }
+
+    fn metadata(&self) -> &Metadata<'_> {
+        // NOTE set right after the callsite is created, before it is registered
+        self.metadata.get().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fmt, path::PathBuf, sync::Arc};
+
+    use tracing_core::{
+        field::{Field, Visit},
+        span, Dispatch, Subscriber,
+    };
+
+    use super::*;
+    use crate::{Encoding, Table, Tag};
+
+    /// Records the events it receives as `<level> <target> <file>:<line> <fields>`.
+    #[derive(Clone, Default)]
+    struct Recorder {
+        events: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, _: &span::Attributes<'_>) -> span::Id {
+            span::Id::from_u64(1)
+        }
+
+        fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+
+        fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            struct Fields(String);
+
+            impl Visit for Fields {
+                fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+                    self.0.push_str(&format!(" {field}={value:?}"));
+                }
+            }
+
+            let metadata = event.metadata();
+            let mut fields = Fields(format!(
+                "{} {} {}:{}",
+                metadata.level(),
+                metadata.target(),
+                metadata.file().unwrap_or("?"),
+                metadata.line().unwrap_or(0),
+            ));
+            event.record(&mut fields);
+            self.events.lock().unwrap().push(fields.0);
+        }
+
+        fn enter(&self, _: &span::Id) {}
+
+        fn exit(&self, _: &span::Id) {}
+    }
+
+    #[test]
+    fn events() {
+        let table = Table::with_entries(
+            &[(0, Tag::Warn, "x={=u8}"), (1, Tag::Println, "hello")],
+            Encoding::Raw,
+        );
+        let location = Location {
+            file: PathBuf::from("src/main.rs"),
+            line: 42,
+            column: None,
+            module: "app::main".to_owned(),
+            function: None,
+        };
+
+        let recorder = Recorder::default();
+        dispatcher::with_default(&Dispatch::new(recorder.clone()), || {
+            let (frame, _) = table.decode(&[0, 0, 7]).unwrap();
+            emit_frame(&frame, Some(&location));
+            emit_frame(&frame, Some(&location));
+            let (frame, _) = table.decode(&[1, 0]).unwrap();
+            emit_frame(&frame, None);
+        });
+
+        assert_eq!(
+            *recorder.events.lock().unwrap(),
+            [
+                "WARN app::main src/main.rs:42 message=x=7 index=0",
+                "WARN app::main src/main.rs:42 message=x=7 index=0",
+                "INFO defmt ?:0 message=hello index=1",
+            ]
+        );
+    }
+}
@@ -0,0 +1,99 @@
+//! Compares how many bytes typical log statements take on the wire.
+//!
+//! The wire format is selected at compile time, so run this once per encoding:
+//!
+//! ``` console
+//! $ cargo bench -p defmt --bench encoding_size --features unstable-test
+//! $ cargo bench -p defmt --bench encoding_size --features unstable-test,encoding-raw
+//! $ cargo bench -p defmt --bench encoding_size --features unstable-test,encoding-varint
+//! $ cargo bench -p defmt --bench encoding_size --features unstable-test,encoding-raw,encoding-varint
+//! $ cargo bench -p defmt --bench encoding_size --features unstable-test,encoding-lzss
+//! $ cargo bench -p defmt --bench encoding_size --features unstable-test,encoding-rzcobs-crc
+//! ```
+//!
+//! The frames are encoded twice; the second total shows how well an encoding that keeps a
+//! history across frames (`lzss`) does once the history is filled.
+//!
+//! NOTE `println!` is used so that no `DEFMT_LOG` filter is needed. The timestamp is not included,
+//! since it is mocked out when testing. Interned string indices are mocked as well and are small,
+//! like in small to medium sized firmware.
+
+use defmt::{export::fetch_bytes, Encoder};
+
+#[derive(defmt::Format)]
+enum State {
+    Idle,
+    Busy { job: u8, progress: u16 },
+}
+
+fn main() {
+    let encoding = if cfg!(feature = "encoding-raw") {
+        "raw"
+    } else if cfg!(feature = "encoding-lzss") {
+        "lzss"
+    } else if cfg!(feature = "encoding-rzcobs-crc") {
+        "rzcobs-crc"
+    } else {
+        "rzcobs"
+    };
+    let varint = if cfg!(feature = "encoding-varint") {
+        "+varint"
+    } else {
+        ""
+    };
+
+    let statements: [(&str, fn()); 9] = [
+        ("no arguments", || defmt::println!("boot")),
+        ("small u16 + i16", || {
+            defmt::println!("adc={=u16} temp={=i16}", 512, -3)
+        }),
+        ("small usize", || defmt::println!("rx {=usize} bytes", 12)),
+        ("small u32", || defmt::println!("tick={=u32}", 1_000)),
+        ("large u32", || {
+            defmt::println!("addr={=u32:#x}", 0x2000_0000)
+        }),
+        ("f32", || defmt::println!("vcc={=f32}", 3.3)),
+        ("derived enum", || {
+            defmt::println!(
+                "{} -> {}",
+                State::Idle,
+                State::Busy {
+                    job: 3,
+                    progress: 40
+                }
+            )
+        }),
+        ("8 byte slice", || {
+            defmt::println!("packet={=[u8]}", &[0x45, 0, 0, 28, 0, 0, 0x40, 0][..])
+        }),
+        ("u64 uptime", || {
+            defmt::println!("uptime={=u64}us", 123_456_789)
+        }),
+    ];
+
+    println!("encoding: {encoding}{varint}");
+    println!("{:<16} {:>6} {:>8}", "statement", "frame", "encoded");
+
+    let frames = statements.map(|(name, log)| {
+        log();
+        (name, fetch_bytes())
+    });
+
+    let mut encoder = Encoder::new();
+    for pass in ["total", "total, repeated"] {
+        let (mut frame_total, mut encoded_total) = (0, 0);
+        for (name, frame) in &frames {
+            let mut encoded = 0;
+            encoder.start_frame(|bytes| encoded += bytes.len());
+            encoder.write(frame, |bytes| encoded += bytes.len());
+            encoder.end_frame(|bytes| encoded += bytes.len());
+
+            if pass == "total" {
+                println!("{:<16} {:>6} {:>8}", name, frame.len(), encoded);
+            }
+            frame_total += frame.len();
+            encoded_total += encoded;
+        }
+        println!("{:<16} {:>6} {:>8}", pass, frame_total, encoded_total);
+    }
+}
@@ -0,0 +1,210 @@
+// LZSS compression of log frames, framed with rzCOBS.
+//
+// Before rzCOBS framing, an encoded frame looks like this:
+//
+//   header   => 1xxxxxxx: the history was reset before this frame, x is the sequence number
+//               0xxxxxxx: this frame refers to the history of the previous frames
+//   groups   => a flags byte, followed by up to 8 items. Bit n of the flags is the type of item n:
+//               0 => literal byte
+//               1 => match of 2 bytes `distance - 1`, `length - 3`: copy `length` bytes starting
+//                    `distance` bytes back in the history (which may overlap the copied bytes)
+//
+// The history is the last 256 bytes of uncompressed frame data and carries over from one frame
+// to the next, so that what frames have in common (the index, the timestamp, ..) compresses well.
+// It is reset every `RESET_INTERVAL` frames, so that a decoder that missed a frame, or attached to
+// a running device, can resume at the next reset.
+//
+// To bound the time spent in the critical section of the logger, the encoder only looks for
+// matches at the last `MAX_CHAIN` positions of the history that start with the same 3 bytes (their
+// hash, to be precise), which it keeps track of in hash chains like zlib.
+
+use super::rzcobs;
+
+const WINDOW_SIZE: usize = 256;
+/// Longest match the encoder looks for. The format allows up to 258 bytes, but every byte of
+/// lookahead costs RAM.
+const MAX_MATCH: usize = 32;
+const MIN_MATCH: usize = 3;
+/// The hash chains have `1 << HASH_BITS` heads
+const HASH_BITS: u32 = 6;
+/// Most positions of the history the encoder compares the lookahead with, per emitted item
+const MAX_CHAIN: usize = 16;
+/// Must be a power of 2 that divides the range of the sequence number
+const RESET_INTERVAL: u8 = 16;
+const RESET_FLAG: u8 = 0x80;
+const SEQUENCE_MASK: u8 = 0x7f;
+
+pub(crate) struct Encoder {
+    rzcobs: rzcobs::Encoder,
+    /// The history (`..history_len`), followed by the lookahead (`history_len..history_len +
+    /// lookahead_len`)
+    buf: [u8; WINDOW_SIZE + MAX_MATCH],
+    history_len: usize,
+    lookahead_len: usize,
+    /// Position of `buf[0]` in the stream, wrapping around. The hash chains use these positions, so
+    /// that they stay valid when the history is shifted.
+    base: u16,
+    /// Number of positions of the history (`..hashed`) that were added to the hash chains
+    hashed: usize,
+    /// The last position of the stream whose first 3 bytes have the hash, for every hash
+    head: [u16; 1 << HASH_BITS],
+    /// Distance from a position to the previous position with the same hash, indexed by position
+    /// modulo `WINDOW_SIZE`, or 0 if there is none in the window
+    prev: [u8; WINDOW_SIZE],
+    /// The group that is currently written: the flags byte and up to 8 items of at most 2 bytes
+    group: [u8; 17],
+    group_len: usize,
+    group_items: u8,
+    sequence: u8,
+}
+
+impl Encoder {
+    pub const fn new() -> Self {
+        Self {
+            rzcobs: rzcobs::Encoder::new(),
+            buf: [0; WINDOW_SIZE + MAX_MATCH],
+            history_len: 0,
+            lookahead_len: 0,
+            base: 0,
+            hashed: 0,
+            head: [0; 1 << HASH_BITS],
+            prev: [0; WINDOW_SIZE],
+            group: [0; 17],
+            group_len: 1,
+            group_items: 0,
+            sequence: 0,
+        }
+    }
+
+    pub fn start_frame(&mut self, mut write: impl FnMut(&[u8])) {
+        self.rzcobs.start_frame(&mut write);
+
+        let mut header = self.sequence & SEQUENCE_MASK;
+        if self.sequence & (RESET_INTERVAL - 1) == 0 {
+            header |= RESET_FLAG;
+            // positions before `base` are not in the history, whatever the hash chains say
+            self.base = self.base.wrapping_add(self.history_len as u16);
+            self.history_len = 0;
+            self.hashed = 0;
+        }
+        self.sequence = (self.sequence + 1) & SEQUENCE_MASK;
+
+        self.rzcobs.write(&[header], &mut write);
+    }
+
+    pub fn end_frame(&mut self, mut write: impl FnMut(&[u8])) {
+        while self.lookahead_len != 0 {
+            self.compress_one(&mut write);
+        }
+        if self.group_items != 0 {
+            self.flush_group(&mut write);
+        }
+
+        self.rzcobs.end_frame(write);
+    }
+
+    pub fn write(&mut self, data: &[u8], mut write: impl FnMut(&[u8])) {
+        for &byte in data {
+            if self.lookahead_len == MAX_MATCH {
+                self.compress_one(&mut write);
+            }
+            self.buf[self.history_len + self.lookahead_len] = byte;
+            self.lookahead_len += 1;
+        }
+    }
+
+    /// Emits a literal or match for the start of the lookahead.
+    ///
+    /// This compares the lookahead with at most `MAX_CHAIN` positions of the history, so it costs
+    /// up to `MAX_CHAIN * MAX_MATCH` byte comparisons, and runs for every logged byte.
+    fn compress_one(&mut self, write: &mut impl FnMut(&[u8])) {
+        let lookahead = self.history_len;
+        let end = self.history_len + self.lookahead_len;
+
+        // positions can only be hashed once their first 3 bytes were written
+        while self.hashed < self.history_len && self.hashed + MIN_MATCH <= end {
+            let position = self.base.wrapping_add(self.hashed as u16);
+            let hash = self.hash(self.hashed);
+            let distance = position.wrapping_sub(self.head[hash]);
+            self.prev[usize::from(position) % WINDOW_SIZE] = match distance {
+                1..=0xff => distance as u8,
+                _ => 0,
+            };
+            self.head[hash] = position;
+            self.hashed += 1;
+        }
+
+        // greedily pick the longest match, the closest one if there are several
+        let mut best_len = 0;
+        let mut best_start = 0;
+        if self.lookahead_len >= MIN_MATCH {
+            let mut position = self.head[self.hash(lookahead)];
+            for _ in 0..MAX_CHAIN {
+                // stale positions from before the window wrap around to large values
+                let start = usize::from(position.wrapping_sub(self.base));
+                if start >= self.hashed {
+                    break;
+                }
+
+                let mut len = 0;
+                while len < self.lookahead_len && self.buf[start + len] == self.buf[lookahead + len]
+                {
+                    len += 1;
+                }
+                if len > best_len {
+                    best_len = len;
+                    best_start = start;
+                }
+
+                match self.prev[usize::from(position) % WINDOW_SIZE] {
+                    0 => break,
+                    distance => position = position.wrapping_sub(u16::from(distance)),
+                }
+            }
+        }
+
+        let consumed = if best_len >= MIN_MATCH {
+            let distance = lookahead - best_start;
+            self.group[0] |= 1 << self.group_items;
+            self.group[self.group_len] = (distance - 1) as u8;
+            self.group[self.group_len + 1] = (best_len - MIN_MATCH) as u8;
+            self.group_len += 2;
+            best_len
+        } else {
+            self.group[self.group_len] = self.buf[lookahead];
+            self.group_len += 1;
+            1
+        };
+
+        self.group_items += 1;
+        if self.group_items == 8 {
+            self.flush_group(write);
+        }
+
+        // move the consumed bytes from the lookahead to the history
+        self.history_len += consumed;
+        self.lookahead_len -= consumed;
+        if self.history_len > WINDOW_SIZE {
+            let excess = self.history_len - WINDOW_SIZE;
+            self.buf
+                .copy_within(excess..self.history_len + self.lookahead_len, 0);
+            self.history_len = WINDOW_SIZE;
+            self.base = self.base.wrapping_add(excess as u16);
+            self.hashed = self.hashed.saturating_sub(excess);
+        }
+    }
+
+    /// Returns the hash of the 3 bytes at `start` of `buf`.
+    fn hash(&self, start: usize) -> usize {
+        let [a, b, c] = [0, 1, 2].map(|i| u32::from(self.buf[start + i]));
+        // Fibonacci hashing: the top bits of the product depend on all bits of the bytes
+        ((a << 16 | b << 8 | c).wrapping_mul(0x9e37_79b9) >> (32 - HASH_BITS)) as usize
+    }
+
+    fn flush_group(&mut self, write: &mut impl FnMut(&[u8])) {
+        self.rzcobs.write(&self.group[..self.group_len], write);
+        self.group[0] = 0;
+        self.group_len = 1;
+        self.group_items = 0;
+    }
+}
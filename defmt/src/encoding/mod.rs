@@ -1,9 +1,35 @@
-#[cfg(all(feature = "encoding-raw", feature = "encoding-rzcobs"))]
+#[cfg(any(
+    all(feature = "encoding-raw", feature = "encoding-rzcobs"),
+    all(feature = "encoding-raw", feature = "encoding-rzcobs-crc"),
+    all(feature = "encoding-raw", feature = "encoding-lzss"),
+    all(feature = "encoding-rzcobs", feature = "encoding-rzcobs-crc"),
+    all(feature = "encoding-rzcobs", feature = "encoding-lzss"),
+    all(feature = "encoding-rzcobs-crc", feature = "encoding-lzss"),
+))]
 compile_error!("Multiple `encoding-*` features are enabled. You may only enable one.");
 
-#[cfg_attr(feature = "encoding-raw", path = "raw.rs")]
-#[cfg_attr(not(feature = "encoding-raw"), path = "rzcobs.rs")]
-mod inner;
+#[cfg(feature = "encoding-lzss")]
+mod lzss;
+#[cfg(feature = "encoding-raw")]
+mod raw;
+// also used for the framing of `lzss` and `rzcobs-crc`
+#[cfg(not(feature = "encoding-raw"))]
+mod rzcobs;
+#[cfg(feature = "encoding-rzcobs-crc")]
+mod rzcobs_crc;
+
+#[cfg(feature = "encoding-lzss")]
+use lzss as inner;
+#[cfg(feature = "encoding-raw")]
+use raw as inner;
+#[cfg(not(any(
+    feature = "encoding-raw",
+    feature = "encoding-lzss",
+    feature = "encoding-rzcobs-crc"
+)))]
+use rzcobs as inner;
+#[cfg(feature = "encoding-rzcobs-crc")]
+use rzcobs_crc as inner;
 
 // This wrapper struct is to avoid copypasting the public docs in all the impls.
 
@@ -16,7 +42,7 @@ mod inner;
 /// `encoding-*` Cargo feature. See `Cargo.toml` for the supported encodings
 /// and their tradeoffs.
 ///
-/// Encodings may perform two functions:
+/// Encodings may perform three functions:
 ///
 /// - Framing: Adds extra data to allow the encoder to know when each frame starts
 /// and ends in the stream. Unframed log frames already contain enough information for
@@ -27,6 +53,7 @@ mod inner;
 /// - Compression: The frame data has rather low entropy (for example, it contains many
 /// zero bytes due to encoding all integers in fixed with, and will likely contain many
 /// repetitions). Compression can decrease the on-the-wire required bandwidth.
+/// - Integrity checking: Adds a checksum to each frame, to detect frames corrupted on the wire.
 ///
 /// defmt provides the `Encoder` separately instead of feeding already-encoded bytes
 /// to the `Logger` because `Logger` implementations may decide to allow
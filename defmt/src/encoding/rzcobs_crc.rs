@@ -0,0 +1,81 @@
+// rzCOBS framing of log frames, with a CRC-16 of the frame appended before framing.
+//
+// The CRC is CRC-16/IBM-3740 (also known as CRC-16/CCITT-FALSE): polynomial 0x1021, initial value
+// 0xffff, not reflected, no final XOR. It is appended in little endian, like all other integers.
+
+use super::rzcobs;
+
+const POLYNOMIAL: u16 = 0x1021;
+const INITIAL: u16 = 0xffff;
+
+pub(crate) struct Encoder {
+    rzcobs: rzcobs::Encoder,
+    crc: u16,
+}
+
+impl Encoder {
+    pub const fn new() -> Self {
+        Self {
+            rzcobs: rzcobs::Encoder::new(),
+            crc: INITIAL,
+        }
+    }
+
+    pub fn start_frame(&mut self, write: impl FnMut(&[u8])) {
+        self.crc = INITIAL;
+        self.rzcobs.start_frame(write)
+    }
+
+    pub fn end_frame(&mut self, mut write: impl FnMut(&[u8])) {
+        self.rzcobs.write(&self.crc.to_le_bytes(), &mut write);
+        self.rzcobs.end_frame(write)
+    }
+
+    pub fn write(&mut self, data: &[u8], write: impl FnMut(&[u8])) {
+        self.crc = crc16(self.crc, data);
+        self.rzcobs.write(data, write)
+    }
+}
+
+/// Bitwise, to not spend 512 bytes of flash on a lookup table.
+fn crc16(mut crc: u16, data: &[u8]) -> u16 {
+    for &byte in data {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ POLYNOMIAL
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+#[cfg(feature = "unstable-test")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_value() {
+        assert_eq!(crc16(INITIAL, b"123456789"), 0x29b1);
+        assert_eq!(crc16(crc16(INITIAL, b"1234"), b"56789"), 0x29b1);
+    }
+
+    #[test]
+    fn it_works() {
+        let mut res: Vec<u8> = Vec::new();
+
+        let mut e = Encoder::new();
+        e.start_frame(|data| res.extend(data));
+        e.write(b"1234", |data| res.extend(data));
+        e.write(b"56789", |data| res.extend(data));
+        e.end_frame(|data| res.extend(data));
+
+        assert_eq!(
+            res,
+            [0x00, b'1', b'2', b'3', b'4', b'5', b'6', b'7', b'8', b'9', 0xb1, 0x29, 0x84, 0x00]
+        );
+    }
+}
@@ -1,5 +1,6 @@
 use super::*;
 
+#[cfg(not(feature = "encoding-varint"))]
 macro_rules! write_to_le_bytes {
     ($($s:ident),*) => {
         $(/// Implementation detail
@@ -9,14 +10,69 @@ macro_rules! write_to_le_bytes {
     };
 }
 
+#[cfg(not(feature = "encoding-varint"))]
 write_to_le_bytes!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
 
+/// Implementation detail
+#[cfg(feature = "encoding-varint")]
+pub fn u8(b: &u8) {
+    write(&[*b])
+}
+
+/// Implementation detail
+#[cfg(feature = "encoding-varint")]
+pub fn i8(b: &i8) {
+    write(&b.to_le_bytes())
+}
+
+#[cfg(feature = "encoding-varint")]
+macro_rules! write_leb128 {
+    ($($u:ident),*) => {
+        $(/// Implementation detail
+        pub fn $u(b: &$u) {
+            // 7 bits per byte (none of the types has a multiple of 7 bits)
+            let mut buf = [0; $u::BITS as usize / 7 + 1];
+            let mut x = *b;
+            let mut len = 0;
+            loop {
+                let byte = (x & 0x7f) as u8;
+                x >>= 7;
+                if x == 0 {
+                    buf[len] = byte;
+                    len += 1;
+                    break;
+                }
+                buf[len] = byte | 0x80;
+                len += 1;
+            }
+            write(&buf[..len])
+        })*
+    };
+}
+
+#[cfg(feature = "encoding-varint")]
+write_leb128!(u16, u32, u64, u128);
+
+#[cfg(feature = "encoding-varint")]
+macro_rules! write_zigzag {
+    ($($s:ident => $u:ident),*) => {
+        $(/// Implementation detail
+        pub fn $s(b: &$s) {
+            // maps 0, -1, 1, -2, .. to 0, 1, 2, 3, .. so that small negative numbers stay small
+            $u(&(((*b << 1) ^ (*b >> ($s::BITS - 1))) as $u))
+        })*
+    };
+}
+
+#[cfg(feature = "encoding-varint")]
+write_zigzag!(i16 => u16, i32 => u32, i64 => u64, i128 => u128);
+
 /// Implementation detail
 pub fn usize(b: &usize) {
-    write(&(*b as u32).to_le_bytes())
+    u32(&(*b as u32))
 }
 
 /// Implementation detail
 pub fn isize(b: &isize) {
-    write(&(*b as i32).to_le_bytes())
+    i32(&(*b as i32))
 }
@@ -52,9 +52,61 @@ pub unsafe fn acquire() {
     extern "Rust" {
         fn _defmt_acquire();
     }
+    #[cfg(feature = "image-id")]
+    image_id();
     _defmt_acquire()
 }
 
+/// Index of the frame that identifies the image, see the `image-id` feature
+///
+/// The `.defmt` section never reaches this index, see `defmt.x.in`.
+#[cfg(feature = "image-id")]
+const IMAGE_ID_INDEX: u16 = 0xffff;
+
+/// Writes the frame that identifies the image at `address`.
+#[cfg(feature = "image-id")]
+fn write_image_id(address: u32) {
+    u16(&IMAGE_ID_INDEX);
+    u32(&address);
+}
+
+/// For testing purposes
+#[cfg(all(feature = "image-id", feature = "unstable-test"))]
+pub fn image_id(address: u32) {
+    write_image_id(address)
+}
+
+/// Writes the frame that identifies this image, before its first log frame.
+///
+/// The image is identified by the address of this function, which the host looks up in the ELF of
+/// each image to find the one to decode the following frames with.
+#[cfg(all(feature = "image-id", not(feature = "unstable-test")))]
+#[export_name = "_defmt_image_id"]
+#[inline(never)]
+fn image_id() {
+    use core::sync::atomic::{AtomicBool, Ordering};
+
+    extern "Rust" {
+        fn _defmt_acquire();
+        fn _defmt_release();
+    }
+
+    static WRITTEN: AtomicBool = AtomicBool::new(false);
+    if WRITTEN.load(Ordering::Relaxed) {
+        return;
+    }
+
+    // SAFETY: the frame is written in its own acquire/release pair, before the caller acquires
+    // the logger for the log frame
+    unsafe { _defmt_acquire() };
+    // checked again now that no other context can write a frame
+    if !WRITTEN.load(Ordering::Relaxed) {
+        WRITTEN.store(true, Ordering::Relaxed);
+        write_image_id(image_id as fn() as usize as u32);
+    }
+    unsafe { _defmt_release() };
+}
+
 /// Only to be used by the defmt macros
 /// Safety: must follow an earlier call to acquire()
 #[cfg(feature = "unstable-test")]
@@ -188,7 +240,7 @@ pub fn fmt_array<T: Format>(a: &[T]) {
 
 /// Implementation detail
 pub fn istr(s: &Str) {
-    write(&s.address.to_le_bytes())
+    u16(&s.address)
 }
 
 /// Implementation detail
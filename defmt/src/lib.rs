@@ -35,11 +35,48 @@ static DEFMT_VERSION: u8 = 0;
 #[used]
 #[cfg_attr(target_os = "macos", link_section = ".defmt,end.ENCODING")]
 #[cfg_attr(not(target_os = "macos"), link_section = ".defmt.end")]
-#[cfg_attr(feature = "encoding-raw", export_name = "_defmt_encoding_ = raw")]
 #[cfg_attr(
-    not(feature = "encoding-raw"),
+    all(feature = "encoding-raw", not(feature = "encoding-varint")),
+    export_name = "_defmt_encoding_ = raw"
+)]
+#[cfg_attr(
+    all(feature = "encoding-lzss", not(feature = "encoding-varint")),
+    export_name = "_defmt_encoding_ = lzss"
+)]
+#[cfg_attr(
+    all(feature = "encoding-rzcobs-crc", not(feature = "encoding-varint")),
+    export_name = "_defmt_encoding_ = rzcobs-crc"
+)]
+#[cfg_attr(
+    all(
+        not(feature = "encoding-raw"),
+        not(feature = "encoding-lzss"),
+        not(feature = "encoding-rzcobs-crc"),
+        not(feature = "encoding-varint")
+    ),
     export_name = "_defmt_encoding_ = rzcobs"
 )]
+#[cfg_attr(
+    all(feature = "encoding-raw", feature = "encoding-varint"),
+    export_name = "_defmt_encoding_ = raw+varint"
+)]
+#[cfg_attr(
+    all(feature = "encoding-lzss", feature = "encoding-varint"),
+    export_name = "_defmt_encoding_ = lzss+varint"
+)]
+#[cfg_attr(
+    all(feature = "encoding-rzcobs-crc", feature = "encoding-varint"),
+    export_name = "_defmt_encoding_ = rzcobs-crc+varint"
+)]
+#[cfg_attr(
+    all(
+        not(feature = "encoding-raw"),
+        not(feature = "encoding-lzss"),
+        not(feature = "encoding-rzcobs-crc"),
+        feature = "encoding-varint"
+    ),
+    export_name = "_defmt_encoding_ = rzcobs+varint"
+)]
 #[allow(missing_docs)]
 #[doc(hidden)]
 pub static DEFMT_ENCODING: u8 = 0;
@@ -1,5 +1,22 @@
 fn main() {
     defmt::info!("hello");
+
+    let x = 42u8;
+    defmt::info!("x={x}, x={x:x}");
+    defmt::println!("x={x=u8}");
+    defmt::assert!(x == 42, "x={x}");
+    defmt::assert_eq!(x, 42, "x={x}");
+    defmt::unwrap!(Some(x), "x={x}");
+
+    let addr = [192u8, 168, 0, 1];
+    defmt::info!(
+        "{=[u8; 4]:ipv4} {=u32:ipv4} {:mac} {=[u8]:hexdump}",
+        &addr,
+        0xc0a8_0001u32,
+        [0u8; 6],
+        &addr[..]
+    );
+    defmt::info!("{=u16:@reg_status}", 0x1234);
 }
 
 #[defmt::global_logger]
@@ -36,6 +36,9 @@
 //
 // - the mocked index is 7 bits so its LEB128 encoding is the input byte
 
+// NOTE the `encoding-varint` wire format is tested in `encode_varint.rs`
+#![cfg(not(feature = "encoding-varint"))]
+
 use defmt::{export::fetch_string_index, write, Debug2Format, Display2Format, Format, Formatter};
 
 // Increase the 7-bit mocked interned index
@@ -92,6 +95,30 @@ fn write() {
     ]);
 }
 
+#[test]
+fn write_named_args() {
+    let index = fetch_string_index();
+    let temp = -3i16;
+    let id = 42u8;
+
+    let g = defmt::export::make_formatter();
+    write!(g, "temp={temp=i16} id={id=u8} again={temp=i16}");
+    check!([
+        index, // "temp={0=i16} id={1=u8} again={0=i16}"
+        temp,  // i16 value
+        id,    // u8 value
+    ]);
+
+    let g = defmt::export::make_formatter();
+    write!(g, "{=u8} {id=u8:x} {=u8}", 1u8, 2u8);
+    check!([
+        inc(index, 1), // "{=u8} {2=u8:x} {=u8}"
+        1u8,           // first positional argument
+        2u8,           // second positional argument
+        id,            // named argument
+    ]);
+}
+
 #[test]
 fn bitfields_mixed() {
     let index = fetch_string_index();
@@ -1008,3 +1035,16 @@ fn core_fmt_adapters() {
     let index = fetch_string_index();
     check_format!(&Display2Format(&123u8), [index, b'1', b'2', b'3', 0xffu8]);
 }
+
+#[cfg(feature = "image-id")]
+#[test]
+fn image_id() {
+    defmt::export::image_id(0x0801_0001);
+    assert_eq!(
+        defmt::export::fetch_bytes(),
+        [
+            0xff, 0xff, // index of the image ID frame
+            0x01, 0x00, 0x01, 0x08, // address that identifies the image
+        ]
+    );
+}
@@ -0,0 +1,69 @@
+// NOTE run these with `cargo test -p defmt --features unstable-test,encoding-lzss`. The format is
+// described in `src/encoding/lzss.rs`.
+#![cfg(feature = "encoding-lzss")]
+
+use defmt::Encoder;
+
+fn encode(encoder: &mut Encoder, frame: &[u8]) -> Vec<u8> {
+    let mut encoded = Vec::new();
+    encoder.start_frame(|bytes| encoded.extend_from_slice(bytes));
+    encoder.write(frame, |bytes| encoded.extend_from_slice(bytes));
+    encoder.end_frame(|bytes| encoded.extend_from_slice(bytes));
+    encoded
+}
+
+#[test]
+fn matches() {
+    let mut encoder = Encoder::new();
+
+    #[rustfmt::skip]
+    assert_eq!(
+        encode(&mut encoder, b"abcabcabc"),
+        [
+            0x00, // rzCOBS: separator before the first frame
+            0x80, // reset, sequence number 0
+            0b1000, b'a', b'b', b'c', 2, 3, // 3 literals and a match of 6 bytes at distance 3
+            0x80, 0x00, // rzCOBS: end of frame
+        ]
+    );
+
+    #[rustfmt::skip]
+    assert_eq!(
+        encode(&mut encoder, b"abcab"),
+        [
+            0x01, // sequence number 1
+            0b1, 2, 2, // match of 5 bytes at distance 3, into the previous frame
+            0x70, 0x00, // rzCOBS: end of frame
+        ]
+    );
+}
+
+#[test]
+fn periodic_reset() {
+    let mut encoder = Encoder::new();
+
+    for sequence in 0..40 {
+        let encoded = encode(&mut encoder, b"abc");
+        let header = if sequence == 0 {
+            encoded[1]
+        } else {
+            encoded[0]
+        };
+
+        if sequence % 16 == 0 {
+            assert_eq!(header, 0x80 | sequence);
+        } else {
+            assert_eq!(header, sequence);
+        }
+    }
+}
+
+#[test]
+fn compresses_repetitive_frames() {
+    let mut encoder = Encoder::new();
+    let frame = [1, 0, 0x12, 0x34, 0x56, 0x78, 42, 0, 0, 0, 7, 0];
+    encode(&mut encoder, &frame);
+
+    // after the first frame, the whole frame is a single match
+    assert_eq!(encode(&mut encoder, &frame), [0x01, 0b1, 11, 9, 0x70, 0x00]);
+}
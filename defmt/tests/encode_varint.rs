@@ -0,0 +1,111 @@
+// NOTE run these with `cargo test -p defmt --features unstable-test,encoding-varint`. See
+// `encode.rs` for how string interning is mocked; the mocked index is 7 bits, so its LEB128
+// encoding is a single byte.
+#![cfg(feature = "encoding-varint")]
+
+use defmt::{
+    export::{fetch_bytes, fetch_string_index, make_formatter},
+    write,
+};
+
+fn index() -> u8 {
+    let index = fetch_string_index();
+    assert!(index < 0x80);
+    index as u8
+}
+
+#[test]
+fn unsigned() {
+    let index = index();
+    let g = make_formatter();
+    write!(
+        g,
+        "{=u8} {=u16} {=u32} {=u64} {=u128} {=usize}",
+        200,
+        1,
+        300,
+        u64::MAX,
+        0,
+        127
+    );
+    #[rustfmt::skip]
+    assert_eq!(
+        fetch_bytes(),
+        [
+            index,
+            200, // u8 is not LEB128-encoded
+            1,
+            0xac, 0x02,
+            0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01,
+            0,
+            127,
+        ]
+    );
+}
+
+#[test]
+fn signed() {
+    let index = index();
+    let g = make_formatter();
+    write!(
+        g,
+        "{=i8} {=i16} {=i32} {=i64} {=i128} {=isize}",
+        -1,
+        -1,
+        64,
+        -65,
+        i128::MIN,
+        1
+    );
+    #[rustfmt::skip]
+    assert_eq!(
+        fetch_bytes(),
+        [
+            index,
+            0xff, // i8 is not zigzag-encoded
+            1,
+            0x80, 0x01,
+            0x81, 0x01,
+            0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+            0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x03,
+            2,
+        ]
+    );
+}
+
+#[test]
+fn lengths_and_indices() {
+    let index = index();
+    let g = make_formatter();
+    write!(g, "{=str} {=[u8]} {=?}", "hi", &[1, 2][..], 'a');
+    assert_eq!(
+        fetch_bytes(),
+        [
+            index,
+            2, // length of "hi"
+            b'h',
+            b'i',
+            2, // length of the slice
+            1,
+            2,
+            index + 1, // "{=char}" / impl Format for char
+            b'a',      // chars stay 4 bytes wide
+            0,
+            0,
+            0,
+        ]
+    );
+}
+
+#[cfg(feature = "image-id")]
+#[test]
+fn image_id() {
+    defmt::export::image_id(0x0801_0001);
+    assert_eq!(
+        fetch_bytes(),
+        [
+            0xff, 0xff, 0x03, // index of the image ID frame
+            0x81, 0x80, 0x84, 0x40, // address that identifies the image
+        ]
+    );
+}
@@ -0,0 +1,3 @@
+fn main() {
+    defmt::info!("{=u16:ipv4}", 42)
+}
@@ -0,0 +1,3 @@
+fn main() {
+    defmt::info!("{=[u8; 4]:mac}", &[0; 4])
+}
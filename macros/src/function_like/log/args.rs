@@ -1,7 +1,10 @@
+use std::fmt::Write as _;
+
+use proc_macro_error::abort;
 use syn::{
     parse::{self, Parse, ParseStream},
     punctuated::Punctuated,
-    Expr, LitStr, Token,
+    Expr, ExprPath, Ident, LitStr, Token,
 };
 
 pub(crate) struct Args {
@@ -22,3 +25,96 @@ impl Parse for Args {
         })
     }
 }
+
+impl Args {
+    /// Captures variables named in the format string, like `format_args!` does.
+    ///
+    /// Parameters like `{x}` or `{x=u8:x}` are rewritten to positional ones (`{2}`, `{2=u8:x}`),
+    /// where the index comes after all explicitly passed arguments, and `x` is appended to the
+    /// formatting arguments. Using the same name several times refers to the same argument.
+    pub(crate) fn capture_named_args(self) -> Self {
+        let Args {
+            format_string,
+            mut formatting_args,
+        } = self;
+
+        let span = format_string.span();
+        let string = format_string.value();
+        let given_arg_count = formatting_args.as_ref().map_or(0, Punctuated::len);
+
+        let mut rewritten = String::with_capacity(string.len());
+        let mut names = Vec::<String>::new();
+        let mut rest = &*string;
+        while let Some(brace_pos) = rest.find('{') {
+            rewritten.push_str(&rest[..brace_pos]);
+            rest = &rest[brace_pos..];
+
+            if rest.starts_with("{{") {
+                // escaped brace
+                rewritten.push_str("{{");
+                rest = &rest["{{".len()..];
+                continue;
+            }
+
+            let param = match rest.find('}') {
+                Some(end) => &rest[1..end],
+                // unterminated parameter; let the parser report it
+                None => break,
+            };
+            rest = &rest[param.len() + 2..];
+
+            let name_len = param
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(param.len());
+            let name = &param[..name_len];
+            if !name.starts_with(|c: char| c.is_alphabetic() || c == '_') {
+                // positional or implicit parameter; keep it as it is
+                write!(rewritten, "{{{param}}}").ok();
+                continue;
+            }
+
+            let index = match names.iter().position(|known| known == name) {
+                Some(position) => position,
+                None => {
+                    names.push(name.to_string());
+                    names.len() - 1
+                }
+            };
+            write!(
+                rewritten,
+                "{{{}{}}}",
+                given_arg_count + index,
+                &param[name_len..]
+            )
+            .ok();
+        }
+        rewritten.push_str(rest);
+
+        if names.is_empty() {
+            return Args {
+                format_string,
+                formatting_args,
+            };
+        }
+
+        let exprs = formatting_args.get_or_insert_with(Punctuated::new);
+        for name in names {
+            let mut ident = match syn::parse_str::<Ident>(&name) {
+                Ok(ident) => ident,
+                Err(_) => abort!(span, "invalid argument name `{}` in format string", name),
+            };
+            ident.set_span(span);
+
+            exprs.push(Expr::Path(ExprPath {
+                attrs: vec![],
+                qself: None,
+                path: ident.into(),
+            }));
+        }
+
+        Args {
+            format_string: LitStr::new(&rewritten, span),
+            formatting_args,
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use defmt_parser::{Fragment, Parameter, Type};
+use defmt_parser::{DisplayHint, Fragment, Parameter, Type};
 use proc_macro2::{Ident as Ident2, Span as Span2, TokenStream as TokenStream2};
 use proc_macro_error::abort;
 use quote::{format_ident, quote};
@@ -39,6 +39,10 @@ impl Codegen {
             )
         }
 
+        for param in &params {
+            check_hint(param, span);
+        }
+
         let mut exprs = vec![];
         let mut patterns = vec![];
 
@@ -59,6 +63,94 @@ impl Codegen {
     }
 }
 
+/// Rejects display hints that cannot be applied to the type of the parameter.
+fn check_hint(param: &Parameter, span: Span2) {
+    let (name, expected, is_supported) = match &param.hint {
+        Some(DisplayHint::NoHint {
+            sign, precision, ..
+        }) => return check_sign_and_precision(param, *sign, *precision, span),
+        Some(DisplayHint::Ipv4) => (
+            "ipv4",
+            "`u32` and `[u8; 4]`",
+            matches!(
+                param.ty,
+                Type::U32 | Type::U8Array(4) | Type::FormatArray(4)
+            ),
+        ),
+        Some(DisplayHint::Ipv6) => (
+            "ipv6",
+            "`[u8; 16]`",
+            matches!(param.ty, Type::U8Array(16) | Type::FormatArray(16)),
+        ),
+        Some(DisplayHint::Mac) => (
+            "mac",
+            "`[u8; 6]`",
+            matches!(param.ty, Type::U8Array(6) | Type::FormatArray(6)),
+        ),
+        Some(DisplayHint::Uuid) => (
+            "uuid",
+            "`[u8; 16]`",
+            matches!(param.ty, Type::U8Array(16) | Type::FormatArray(16)),
+        ),
+        Some(DisplayHint::Hexdump) => (
+            "hexdump",
+            "`[u8]` and `[u8; N]`",
+            matches!(
+                param.ty,
+                Type::U8Slice | Type::U8Array(_) | Type::FormatSlice | Type::FormatArray(_)
+            ),
+        ),
+        _ => return,
+    };
+
+    // the type of `Format` arguments is only known to the decoder
+    if !is_supported && !matches!(param.ty, Type::Format) {
+        abort!(
+            span,
+            "the `{}` display hint is only supported for {}",
+            name,
+            expected
+        )
+    }
+}
+
+/// Rejects sign and precision hints on types that cannot make use of them.
+fn check_sign_and_precision(param: &Parameter, sign: bool, precision: Option<usize>, span: Span2) {
+    // `Format`, `Debug` and `Display` arguments may well be numbers or strings, so only known
+    // types are rejected
+    let is_integer = matches!(
+        param.ty,
+        Type::I8
+            | Type::I16
+            | Type::I32
+            | Type::I64
+            | Type::I128
+            | Type::Isize
+            | Type::U8
+            | Type::U16
+            | Type::U32
+            | Type::U64
+            | Type::U128
+            | Type::Usize
+            | Type::BitField(_)
+    );
+    let is_non_numeric = matches!(
+        param.ty,
+        Type::Bool | Type::Char | Type::U8Slice | Type::U8Array(_)
+    );
+    let is_string = matches!(param.ty, Type::Str | Type::IStr);
+
+    if sign && (is_non_numeric || is_string) {
+        abort!(span, "the `+` display hint is only supported for numbers")
+    }
+    if precision.is_some() && (is_non_numeric || is_integer) {
+        abort!(
+            span,
+            "the precision display hint is only supported for floats and strings"
+        )
+    }
+}
+
 fn encode_arg(ty: &Type, params: &[Parameter], arg_index: usize, arg: &Ident2) -> TokenStream2 {
     match ty {
         Type::I8 => quote!(defmt::export::i8(#arg)),
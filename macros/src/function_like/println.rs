@@ -13,6 +13,7 @@ pub(crate) fn expand(args: TokenStream) -> TokenStream {
 }
 
 pub(crate) fn expand_parsed(args: Args) -> TokenStream2 {
+    let args = args.capture_named_args();
     let format_string = args.format_string.value();
     let fragments = match defmt_parser::parse(&format_string, ParserMode::Strict) {
         Ok(args) => args,
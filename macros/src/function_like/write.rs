@@ -17,6 +17,7 @@ pub(crate) fn expand(args: TokenStream) -> TokenStream {
         ..
     } = parse_macro_input!(args as Args);
 
+    let log_args = log_args.capture_named_args();
     let format_string = log_args.format_string.value();
     let fragments = match defmt_parser::parse(&format_string, ParserMode::Strict) {
         Ok(args) => args,
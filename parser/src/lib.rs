@@ -34,11 +34,30 @@ pub enum TimePrecision {
     Seconds,
 }
 
+/// Alignment of a value that is narrower than its field width
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Alignment {
+    /// `<`
+    Left,
+    /// `^`
+    Center,
+    /// `>`
+    Right,
+}
+
 /// All display hints
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum DisplayHint {
     NoHint {
         zero_pad: usize,
+        /// `+`, always prints the sign of numbers
+        sign: bool,
+        /// `<`, `^` or `>`; if `None` numbers are right-aligned and everything else left-aligned
+        align: Option<Alignment>,
+        /// Minimum number of characters, padded with spaces
+        width: usize,
+        /// `.N`, number of decimal places of floats or maximum number of characters of strings
+        precision: Option<usize>,
     },
     /// `:x` OR `:X`
     Hexadecimal {
@@ -47,6 +66,7 @@ pub enum DisplayHint {
         zero_pad: usize,
     },
     /// `:b`
+    #[rustfmt::skip]
     Binary {
         alternate: bool,
         zero_pad: usize,
@@ -59,6 +79,16 @@ pub enum DisplayHint {
     Microseconds,
     /// `:iso8601{ms,s}`, formats integers as timestamp in ISO8601 date time format
     ISO8601(TimePrecision),
+    /// `:ipv4`, formats a `u32` or `[u8; 4]` as an IPv4 address
+    Ipv4,
+    /// `:ipv6`, formats a `[u8; 16]` as an IPv6 address
+    Ipv6,
+    /// `:mac`, formats a `[u8; 6]` as a MAC address
+    Mac,
+    /// `:uuid`, formats a `[u8; 16]` as a UUID
+    Uuid,
+    /// `:hexdump`, formats bytes as a multi-line offset/hex/ASCII view like `xxd`
+    Hexdump,
     /// `__internal_bitflags_NAME` instructs the decoder to print the flags that are set, instead of
     /// the raw value.
     Bitflags {
@@ -66,6 +96,9 @@ pub enum DisplayHint {
         package: String,
         disambiguator: String,
     },
+    /// `:@NAME`, formatted by a formatter the host registers for `NAME`, or as if there was no
+    /// hint if there is none.
+    Custom(String),
     /// Display hints currently not supported / understood
     Unknown(String),
 }
@@ -74,6 +107,31 @@ pub enum DisplayHint {
 fn parse_display_hint(mut s: &str) -> Option<DisplayHint> {
     const BITFLAGS_HINT_START: &str = "__internal_bitflags_";
 
+    if let Some(name) = s.strip_prefix('@') {
+        let is_valid =
+            !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        return is_valid.then(|| DisplayHint::Custom(name.into()));
+    }
+
+    // Like in core::fmt, alignment and sign come first. Together with width and precision they
+    // are only supported without a type (e.g. `{=u32:>8}`, but not `{=u32:>8x}`).
+    let align = match s.chars().next() {
+        Some('<') => Some(Alignment::Left),
+        Some('^') => Some(Alignment::Center),
+        Some('>') => Some(Alignment::Right),
+        _ => None,
+    };
+    if align.is_some() {
+        s = &s[1..]; // alignment characters are always 1 byte
+    }
+
+    let sign = if let Some(rest) = s.strip_prefix('+') {
+        s = rest;
+        true
+    } else {
+        false
+    };
+
     // The `#` comes before any padding hints (I think this matches core::fmt).
     // It is ignored for types that don't have an alternate representation.
     let alternate = if matches!(s.chars().next(), Some('#')) {
@@ -91,6 +149,27 @@ fn parse_display_hint(mut s: &str) -> Option<DisplayHint> {
         0 // default behavior is the same as no zero-padding.
     };
 
+    let width = if s.starts_with(|c: char| c.is_ascii_digit()) {
+        let (rest, columns) = parse_integer::<usize>(s)?;
+        s = rest;
+        columns
+    } else {
+        0
+    };
+
+    let precision = if let Some(rest) = s.strip_prefix('.') {
+        let (rest, places) = parse_integer::<usize>(rest)?;
+        s = rest;
+        Some(places)
+    } else {
+        None
+    };
+
+    let is_typed = !s.is_empty();
+    if is_typed && (align.is_some() || sign || width != 0 || precision.is_some()) {
+        return None;
+    }
+
     if let Some(stripped) = s.strip_prefix(BITFLAGS_HINT_START) {
         let parts = stripped.split('@').collect::<Vec<_>>();
         match *parts {
@@ -108,7 +187,13 @@ fn parse_display_hint(mut s: &str) -> Option<DisplayHint> {
     }
 
     Some(match s {
-        "" => DisplayHint::NoHint { zero_pad },
+        "" => DisplayHint::NoHint {
+            zero_pad,
+            sign,
+            align,
+            width,
+            precision,
+        },
         "us" => DisplayHint::Microseconds,
         "a" => DisplayHint::Ascii,
         "b" => DisplayHint::Binary {
@@ -128,6 +213,11 @@ fn parse_display_hint(mut s: &str) -> Option<DisplayHint> {
         "iso8601ms" => DisplayHint::ISO8601(TimePrecision::Millis),
         "iso8601s" => DisplayHint::ISO8601(TimePrecision::Seconds),
         "?" => DisplayHint::Debug,
+        "ipv4" => DisplayHint::Ipv4,
+        "ipv6" => DisplayHint::Ipv6,
+        "mac" => DisplayHint::Mac,
+        "uuid" => DisplayHint::Uuid,
+        "hexdump" => DisplayHint::Hexdump,
         _ => return None,
     })
 }
@@ -158,9 +248,15 @@ pub enum Fragment<'f> {
 /// byte-array := '[u8;' spaces integer ']'
 /// spaces := ' '*
 ///
-/// format_spec := [ zero_pad ] type
+/// format_spec := [ align ] [ sign ] [ '#' ] [ zero_pad | width ] [ precision ] type
+/// align := '<' | '^' | '>'
+/// sign := '+'
 /// zero_pad := '0' integer
+/// width := integer
+/// precision := '.' integer
 /// type := 'a' | 'b' | 'o' | 'x' | 'X' | '?' | 'us'
+///
+/// (`align`, `sign`, `width` and `precision` are only allowed if `type` is omitted)
 /// ```
 #[derive(Debug, PartialEq)]
 struct Param {
@@ -727,6 +823,62 @@ mod tests {
             })
         );
 
+        assert_eq!(
+            parse_param(":ipv4", ParserMode::Strict),
+            Ok(Param {
+                index: None,
+                ty: Type::Format,
+                hint: Some(DisplayHint::Ipv4),
+            })
+        );
+
+        assert_eq!(
+            parse_param(":ipv6", ParserMode::Strict),
+            Ok(Param {
+                index: None,
+                ty: Type::Format,
+                hint: Some(DisplayHint::Ipv6),
+            })
+        );
+
+        assert_eq!(
+            parse_param(":mac", ParserMode::Strict),
+            Ok(Param {
+                index: None,
+                ty: Type::Format,
+                hint: Some(DisplayHint::Mac),
+            })
+        );
+
+        assert_eq!(
+            parse_param(":uuid", ParserMode::Strict),
+            Ok(Param {
+                index: None,
+                ty: Type::Format,
+                hint: Some(DisplayHint::Uuid),
+            })
+        );
+
+        assert_eq!(
+            parse_param(":hexdump", ParserMode::Strict),
+            Ok(Param {
+                index: None,
+                ty: Type::Format,
+                hint: Some(DisplayHint::Hexdump),
+            })
+        );
+
+        assert_eq!(
+            parse_param("=u16:@reg_status", ParserMode::Strict),
+            Ok(Param {
+                index: None,
+                ty: Type::U16,
+                hint: Some(DisplayHint::Custom("reg_status".to_string())),
+            })
+        );
+        assert!(parse_param(":@", ParserMode::Strict).is_err());
+        assert!(parse_param(":@reg-status", ParserMode::Strict).is_err());
+
         assert_eq!(
             parse_param(":unknown", ParserMode::ForwardsCompatible),
             Ok(Param {
@@ -909,11 +1061,102 @@ mod tests {
             Ok(Param {
                 index: None,
                 ty: Type::Format,
-                hint: Some(DisplayHint::NoHint { zero_pad: 2 })
+                hint: Some(DisplayHint::NoHint {
+                    zero_pad: 2,
+                    sign: false,
+                    align: None,
+                    width: 0,
+                    precision: None,
+                })
             })
         )
     }
 
+    #[test]
+    fn width_alignment_precision() {
+        assert_eq!(
+            parse_param("=f32:.3", ParserMode::Strict),
+            Ok(Param {
+                index: None,
+                ty: Type::F32,
+                hint: Some(DisplayHint::NoHint {
+                    zero_pad: 0,
+                    sign: false,
+                    align: None,
+                    width: 0,
+                    precision: Some(3),
+                })
+            })
+        );
+
+        assert_eq!(
+            parse_param("=u32:>8", ParserMode::Strict),
+            Ok(Param {
+                index: None,
+                ty: Type::U32,
+                hint: Some(DisplayHint::NoHint {
+                    zero_pad: 0,
+                    sign: false,
+                    align: Some(Alignment::Right),
+                    width: 8,
+                    precision: None,
+                })
+            })
+        );
+
+        assert_eq!(
+            parse_param("=str:<12", ParserMode::Strict),
+            Ok(Param {
+                index: None,
+                ty: Type::Str,
+                hint: Some(DisplayHint::NoHint {
+                    zero_pad: 0,
+                    sign: false,
+                    align: Some(Alignment::Left),
+                    width: 12,
+                    precision: None,
+                })
+            })
+        );
+
+        assert_eq!(
+            parse_param("=i16:+", ParserMode::Strict),
+            Ok(Param {
+                index: None,
+                ty: Type::I16,
+                hint: Some(DisplayHint::NoHint {
+                    zero_pad: 0,
+                    sign: true,
+                    align: None,
+                    width: 0,
+                    precision: None,
+                })
+            })
+        );
+
+        assert_eq!(
+            parse_param("=f64:^+10.2", ParserMode::Strict),
+            Ok(Param {
+                index: None,
+                ty: Type::F64,
+                hint: Some(DisplayHint::NoHint {
+                    zero_pad: 0,
+                    sign: true,
+                    align: Some(Alignment::Center),
+                    width: 10,
+                    precision: Some(2),
+                })
+            })
+        );
+
+        // only allowed without a type
+        assert!(parse_param("=u32:>8x", ParserMode::Strict).is_err());
+        assert!(parse_param("=i16:+x", ParserMode::Strict).is_err());
+        assert!(parse_param("=f32:.3?", ParserMode::Strict).is_err());
+        // missing precision
+        assert!(parse_param("=f32:.", ParserMode::Strict).is_err());
+    }
+
     #[test]
     fn index() {
         // implicit
@@ -0,0 +1,209 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use defmt_decoder::{ArgsSize, DecodeError, Encoding, Location, Table};
+
+/// Frames and bytes on the wire, by index
+type Traffic = BTreeMap<u64, (usize, usize)>;
+
+/// Prints what each log statement costs, and the totals per module and crate.
+///
+/// If a `capture` of the data sent by the device is given, the log statements are ranked by the
+/// bytes they sent.
+pub(crate) fn analyze(
+    elf: &Path,
+    debug_file: Option<PathBuf>,
+    capture: Option<PathBuf>,
+) -> anyhow::Result<()> {
+    let (bytes, table) = crate::read_elf(elf)?;
+    let locs = table.get_locations(&crate::read_debug_info(elf, &bytes, debug_file)?)?;
+    let traffic = capture
+        .map(|capture| fs::read(capture).map(|data| traffic(&table, &data)))
+        .transpose()?;
+
+    let mut indices = table
+        .indices()
+        .map(|index| index as u64)
+        .collect::<Vec<_>>();
+    if let Some(traffic) = &traffic {
+        // most bytes first
+        indices.sort_by_key(|index| std::cmp::Reverse(traffic.get(index).map_or(0, |t| t.1)));
+    }
+
+    let mut totals = BTreeMap::<String, Total>::new();
+    println!(
+        "index   level  args  {}location / format",
+        traffic_header(&traffic)
+    );
+    for index in indices {
+        let entry = table.entry(index as usize).unwrap();
+        let args = table.args_size(index as usize).unwrap_or_default();
+        let used = traffic
+            .as_ref()
+            .map(|traffic| traffic.get(&index).copied().unwrap_or_default());
+        let loc = locs.get(&index);
+
+        println!(
+            "{index:#06x}  {level:5}  {args:>4}  {traffic}{location}",
+            level = format!("{:?}", entry.tag()).to_uppercase(),
+            traffic = used.map_or(String::new(), |(frames, bytes)| format!(
+                "{frames:>7}  {bytes:>9}  "
+            )),
+            location = display_location(loc),
+        );
+        println!("        {:?}", entry.string());
+
+        let module = loc.map_or("<unknown>", |loc| &*loc.module);
+        let krate = module.split("::").next().unwrap_or(module);
+        for (key, path) in [("crate", krate), ("module", module)] {
+            let total = totals.entry(format!("{key} {path}")).or_default();
+            total.statements += 1;
+            total.args = total.args + args;
+            if let Some((frames, bytes)) = used {
+                total.frames += frames;
+                total.bytes += bytes;
+            }
+        }
+    }
+
+    println!();
+    println!(
+        "statements  args  {}crate / module",
+        traffic_header(&traffic)
+    );
+    for (path, total) in totals {
+        let traffic = match traffic {
+            Some(_) => format!("{:>7}  {:>9}  ", total.frames, total.bytes),
+            None => String::new(),
+        };
+        println!(
+            "{:>10}  {:>4}  {traffic}{path}",
+            total.statements, total.args
+        );
+    }
+
+    // the `_defmt_*` functions are shared by all log statements; the code of each call site is
+    // inlined into the function that logs, so it can't be told apart from the rest of that function
+    println!();
+    let functions = defmt_decoder::defmt_function_sizes(&bytes)?;
+    println!(
+        "shared by all log statements: {} bytes of code in the `_defmt_*` functions of the global logger",
+        functions.values().sum::<u64>()
+    );
+    for (name, size) in functions {
+        println!("{size:>8}  {name}");
+    }
+
+    Ok(())
+}
+
+#[derive(Default)]
+struct Total {
+    statements: usize,
+    args: ArgsSize,
+    frames: usize,
+    bytes: usize,
+}
+
+fn traffic_header(traffic: &Option<Traffic>) -> &'static str {
+    match traffic {
+        Some(_) => " frames  wire bytes  ",
+        None => "",
+    }
+}
+
+fn display_location(loc: Option<&Location>) -> String {
+    match loc {
+        Some(loc) => format!(
+            "{} @ {}:{}",
+            loc.function.as_ref().unwrap_or(&loc.module),
+            loc.file.display(),
+            loc.line
+        ),
+        None => "<unknown location>".to_string(),
+    }
+}
+
+/// Counts the frames in `data`, and their encoded size, by index.
+fn traffic(table: &Table, data: &[u8]) -> Traffic {
+    let mut traffic = Traffic::new();
+    let mut count = |index, bytes| {
+        let (frames, total) = traffic.entry(index).or_default();
+        *frames += 1;
+        *total += bytes;
+    };
+
+    match table.encoding() {
+        Encoding::Raw => {
+            let mut data = data;
+            while let Ok((frame, consumed)) = table.decode(data) {
+                count(frame.index(), consumed);
+                data = &data[consumed..];
+            }
+        }
+        // the other encodings end each frame with a zero byte
+        _ => {
+            let mut decoder = table.new_stream_decoder();
+            for chunk in data.split_inclusive(|byte| *byte == 0) {
+                decoder.received(chunk);
+                loop {
+                    match decoder.decode() {
+                        Ok(frame) => count(frame.index(), chunk.len()),
+                        Err(DecodeError::UnexpectedEof) => break,
+                        // skip corrupted frames
+                        Err(_) => {}
+                    }
+                }
+            }
+        }
+    }
+
+    traffic
+}
+
+#[cfg(test)]
+mod tests {
+    use defmt_decoder::{StringEntry, TableEntry, Tag};
+
+    use super::*;
+
+    fn table(encoding: Encoding) -> Table {
+        let entries = [(1, Tag::Info, "x={=u8}"), (2, Tag::Println, "hello")]
+            .into_iter()
+            .map(|(index, tag, format)| {
+                let entry = TableEntry::new(StringEntry::new(tag, format.into()), "".into());
+                (index, entry)
+            })
+            .collect();
+        Table::new(entries, encoding)
+    }
+
+    #[test]
+    fn traffic() {
+        #[rustfmt::skip]
+        let data = [
+            1, 0, 42, // x=42
+            2, 0, // hello
+            1, 0, 43, // x=43
+        ];
+        assert_eq!(
+            super::traffic(&table(Encoding::Raw), &data),
+            Traffic::from([(1, (2, 6)), (2, (1, 2))])
+        );
+
+        #[rustfmt::skip]
+        let data = [
+            0, // separator before the first frame
+            1, 42, 0x7a, 0, // x=42
+            0x80, 0, // corrupted frame
+            1, 43, 0x7a, 0, // x=43
+        ];
+        assert_eq!(
+            super::traffic(&table(Encoding::Rzcobs), &data),
+            Traffic::from([(1, (2, 8))])
+        );
+    }
+}
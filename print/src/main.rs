@@ -1,124 +1,447 @@
 use std::{
-    env, fs,
-    io::{self, Read},
+    borrow::Cow,
+    env, fs, io,
     path::{Path, PathBuf},
+    process,
+    sync::Arc,
+    time::Duration,
 };
 
-use anyhow::anyhow;
-use clap::Parser;
-use defmt_decoder::{DecodeError, Frame, Locations, Table};
+use anyhow::{anyhow, bail};
+use clap::{Parser, Subcommand, ValueEnum};
+use defmt_decoder::{
+    exit::{ExitConditions, ExitReason, Level},
+    log::{ColorChoice, DefmtLocation, Hyperlinks, LevelColor, LogFormat, LoggerOptions, Theme},
+    DecodeError, Frame, Frames, LazyLocations, Table,
+};
+use regex::Regex;
+
+use crate::trace::ChromeTrace;
+
+mod analyze;
+mod table;
+mod trace;
 
 /// Prints defmt-encoded logs to stdout
 #[derive(Parser)]
-#[command(name = "defmt-print")]
+#[command(
+    name = "defmt-print",
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 struct Opts {
+    /// The ELF of the firmware. Pass one per image if several images log over the same channel,
+    /// like a bootloader and an application built with the `image-id` feature of `defmt`.
     #[arg(short, required = true, conflicts_with("version"))]
-    elf: Option<PathBuf>,
+    elf: Vec<PathBuf>,
+
+    /// Read the debug info for location info from this file instead of the ELF. By default, a
+    /// debug info file is looked up by the build ID and `.gnu_debuglink` of a stripped ELF.
+    #[arg(long)]
+    debug_file: Option<PathBuf>,
 
     #[arg(long)]
     json: bool,
 
+    /// Print defmt frames with this template instead of the default layout, e.g.
+    /// `{t:>10} [{L}] {m}: {s} ({F}:{l})`. The fields are the timestamp (t), level (L), message (s),
+    /// function or module (m), file path (F), file name (f), line (l) and column (c).
+    #[arg(long, conflicts_with("json"))]
+    log_format: Option<LogFormat>,
+
+    /// When to color the output: `auto` colors it if it is written to a terminal and `NO_COLOR` is
+    /// not set, `always` or `never`. Decoded frames go to stdout and messages of this tool to
+    /// stderr, and each is colored depending on where it goes.
+    #[arg(long, value_name = "WHEN", default_value = "auto")]
+    color: ColorChoice,
+
+    /// Color a log level differently, e.g. `--level-color info=blue`. Can be given several times.
+    #[arg(long, value_name = "LEVEL=COLOR")]
+    level_color: Vec<LevelColor>,
+
+    /// Color module paths, each in a color picked by a hash of its path.
+    #[arg(long)]
+    color_modules: bool,
+
+    /// Make the locations of log statements clickable hyperlinks in terminals that support them.
+    /// Optionally takes the URL as a template, e.g. `vscode://file/{path}:{line}:{column}`; by
+    /// default it is `file://{path}:{line}`.
+    #[arg(long, value_name = "URL", num_args = 0..=1, default_missing_value = "file://{path}:{line}")]
+    hyperlinks: Option<Hyperlinks>,
+
+    /// Read the defmt data from this file instead of stdin, decoding it on multiple threads.
+    #[arg(long)]
+    input: Option<PathBuf>,
+
     #[arg(long)]
     show_skipped_frames: bool,
 
     #[arg(short, long)]
     verbose: bool,
 
+    /// Write the decoded frames to this file, in the Chrome Trace Event format.
+    #[arg(long)]
+    trace_out: Option<PathBuf>,
+
+    /// Exit with code 0 once the message of a frame matches this regex, e.g. `tests passed`, and
+    /// with code 5 if the input ends without a match.
+    #[arg(long, value_name = "REGEX")]
+    exit_on_match: Option<Regex>,
+
+    /// Exit with code 2 once a frame has this level or a more severe one, e.g. `error`.
+    #[arg(long, value_name = "LEVEL", value_parser = defmt_decoder::exit::parse_level)]
+    fail_on_level: Option<Level>,
+
+    /// Exit with code 3 if the other exit conditions are not met within this many seconds.
+    ///
+    /// This is checked after every frame, including frames that fail to decode, and while waiting
+    /// for data.
+    #[arg(long, value_name = "SECS")]
+    timeout: Option<u64>,
+
+    /// Exit with code 4 after decoding this many frames.
+    #[arg(long, value_name = "N")]
+    max_frames: Option<usize>,
+
     #[arg(short = 'V', long)]
     version: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Report the cost of each log statement in encoded bytes, and the code size of the defmt
+    /// functions they share
+    Analyze {
+        elf: PathBuf,
+
+        /// Read the debug info for location info from this file instead of the ELF.
+        #[arg(long)]
+        debug_file: Option<PathBuf>,
+
+        /// Rank the log statements by the bytes they sent in this capture of defmt data.
+        #[arg(long)]
+        capture: Option<PathBuf>,
+    },
+    /// Inspect the tables of format strings of ELF files
+    #[command(subcommand)]
+    Table(TableCommand),
+}
+
+#[derive(Subcommand)]
+enum TableCommand {
+    /// Compare the tables of an old and a new build of the firmware
+    ///
+    /// Fails if the new build uses an index of the old build for a different format string at the
+    /// same location (file and line), in which case logs of devices running the old build have to
+    /// be decoded with the old ELF. A reused index whose log statement moved does not fail; if
+    /// the location is unknown in either build, the index counts as being at the same location.
+    Diff {
+        /// ELF of the build running on the devices
+        old: PathBuf,
+        /// ELF of the build the host tools have
+        new: PathBuf,
+    },
+    /// List the log and `println!` statements of the firmware, sorted by module
+    Catalog {
+        elf: PathBuf,
+
+        /// Read the debug info for location info from this file instead of the ELF.
+        #[arg(long)]
+        debug_file: Option<PathBuf>,
+
+        #[arg(long, value_enum, default_value_t = CatalogFormat::Markdown)]
+        format: CatalogFormat,
+    },
 }
 
-const READ_BUFFER_SIZE: usize = 1024;
+#[derive(Clone, Copy, ValueEnum)]
+enum CatalogFormat {
+    Json,
+    Markdown,
+}
 
 fn main() -> anyhow::Result<()> {
     let Opts {
         elf,
+        debug_file,
         json,
+        log_format,
+        color,
+        level_color,
+        color_modules,
+        hyperlinks,
+        input,
         show_skipped_frames,
         verbose,
+        trace_out,
+        exit_on_match,
+        fail_on_level,
+        timeout,
+        max_frames,
         version,
+        command,
     } = Opts::parse();
 
     if version {
         return print_version();
     }
 
-    defmt_decoder::log::init_logger(verbose, json, move |metadata| match verbose {
+    match command {
+        Some(Command::Analyze {
+            elf,
+            debug_file,
+            capture,
+        }) => return analyze::analyze(&elf, debug_file, capture),
+        Some(Command::Table(TableCommand::Diff { old, new })) => return table::diff(&old, &new),
+        Some(Command::Table(TableCommand::Catalog {
+            elf,
+            debug_file,
+            format,
+        })) => return table::catalog(&elf, debug_file, format),
+        None => {}
+    }
+
+    let theme = level_color
+        .into_iter()
+        .fold(Theme::default(), |theme, LevelColor { level, color }| {
+            theme.level_color(level, color)
+        });
+    let mut logger_options = LoggerOptions::new()
+        .always_include_location(verbose)
+        .json(json)
+        .color(color)
+        .theme(theme.module_colors(color_modules));
+    if let Some(log_format) = log_format {
+        logger_options = logger_options.log_format(log_format);
+    }
+    if let Some(hyperlinks) = hyperlinks {
+        logger_options = logger_options.hyperlinks(hyperlinks);
+    }
+    defmt_decoder::log::init_logger_with_options(logger_options, move |metadata| match verbose {
         false => defmt_decoder::log::is_defmt_frame(metadata), // We display *all* defmt frames, but nothing else.
         true => true,                                          // We display *all* frames.
     });
 
-    let bytes = fs::read(elf.unwrap())?;
-
-    let table = Table::parse(&bytes)?.ok_or_else(|| anyhow!(".defmt data not found"))?;
-    let locs = table.get_locations(&bytes)?;
+    if debug_file.is_some() && elf.len() > 1 {
+        bail!("`--debug-file` can only be used with a single ELF");
+    }
+    // the tables and locations of the images, in the order of the ELFs
+    let mut tables = Vec::new();
+    let mut locs = Vec::new();
+    for elf in &elf {
+        let (bytes, table) = read_elf(elf)?;
+        locs.push(table.lazy_locations(&read_debug_info(elf, &bytes, debug_file.clone())?)?);
+        tables.push(Arc::new(table));
+    }
+    let mut warned_missing_location = false;
+    let mut warned_location_error = false;
 
-    let locs = if table.indices().all(|idx| locs.contains_key(&(idx as u64))) {
-        Some(locs)
-    } else {
-        log::warn!("(BUG) location info is incomplete; it will be omitted from the output");
-        None
-    };
+    let mut trace = trace_out
+        .map(|path| ChromeTrace::create(&path))
+        .transpose()?;
 
-    let mut buf = [0; READ_BUFFER_SIZE];
-    let mut stream_decoder = table.new_stream_decoder();
+    let mut exit_conditions = ExitConditions::new();
+    if let Some(pattern) = exit_on_match {
+        exit_conditions = exit_conditions.exit_on_match(pattern);
+    }
+    if let Some(level) = fail_on_level {
+        exit_conditions = exit_conditions.fail_on_level(level);
+    }
+    if let Some(timeout) = timeout {
+        exit_conditions = exit_conditions.timeout(Duration::from_secs(timeout));
+    }
+    if let Some(max_frames) = max_frames {
+        exit_conditions = exit_conditions.max_frames(max_frames);
+    }
+    let deadline = exit_conditions.deadline();
 
     let current_dir = env::current_dir()?;
-    let mut stdin = io::stdin().lock();
-
-    loop {
-        // read from stdin and push it to the decoder
-        let n = stdin.read(&mut buf)?;
-        // if 0 bytes where read, we reached EOF, so quit
-        if n == 0 {
-            break Ok(());
-        }
-        stream_decoder.received(&buf[..n]);
-
-        // decode the received data
-        loop {
-            match stream_decoder.decode() {
-                Ok(frame) => forward_to_logger(&frame, location_info(&locs, &frame, &current_dir)),
-                Err(DecodeError::UnexpectedEof) => break,
-                Err(DecodeError::Malformed) => match table.encoding().can_recover() {
-                    // if recovery is impossible, abort
-                    false => return Err(DecodeError::Malformed.into()),
-                    // if recovery is possible, skip the current frame and continue with new data
-                    true => {
-                        if show_skipped_frames || verbose {
-                            println!("(HOST) malformed frame skipped");
-                            println!("└─ {} @ {}:{}", env!("CARGO_PKG_NAME"), file!(), line!());
+    let can_recover = tables[0].encoding().can_recover();
+
+    // `image` is the index of the ELF the frame was decoded with
+    // returns why to stop decoding, if any of the exit conditions is met
+    let mut handle = |frame: Result<&Frame, DecodeError>,
+                      image: usize|
+     -> anyhow::Result<Option<ExitReason>> {
+        match frame {
+            Ok(frame) => {
+                // a broken compilation unit only costs the locations of the log statements in it
+                let location_info = match location_info(&mut locs[image], frame, &current_dir) {
+                    Ok(location_info) => location_info,
+                    Err(e) => {
+                        if !warned_location_error {
+                            log::warn!("failed to read location info; it will be omitted for some log statements: {e:#}");
+                            warned_location_error = true;
+                            // the location is missing because of this error, not a bug
+                            warned_missing_location = true;
+                        }
+                        LocationInfo::default()
+                    }
+                };
+                if location_info.file.is_none() && !warned_missing_location {
+                    log::warn!("(BUG) location info is incomplete; it will be omitted for some log statements");
+                    warned_missing_location = true;
+                }
+                if let Some(trace) = &mut trace {
+                    trace.frame(frame, location_info.module_path.as_deref())?;
+                }
+                forward_to_logger(frame, location_info);
+                return Ok(exit_conditions.check(frame));
+            }
+            Err(e) => match can_recover {
+                // if recovery is impossible, abort
+                false => return Err(e.into()),
+                // if recovery is possible, skip the current frame and continue with new data
+                true => {
+                    if show_skipped_frames || verbose {
+                        match e {
+                            DecodeError::ChecksumMismatch => {
+                                println!("(HOST) corrupted frame skipped")
+                            }
+                            _ => println!("(HOST) malformed frame skipped"),
                         }
-                        continue;
+                        println!("└─ {} @ {}:{}", env!("CARGO_PKG_NAME"), file!(), line!());
+                    }
+                }
+            },
+        }
+        Ok(exit_conditions.check_deadline())
+    };
+
+    let mut exit_reason = None;
+    match input {
+        // the image that wrote a frame is only known after decoding the previous frames
+        Some(input) if tables.len() == 1 => {
+            let data = fs::read(input)?;
+            for frame in tables[0].decode_parallel(&data) {
+                exit_reason = match frame {
+                    Ok(frame) => handle(Ok(&frame), 0)?,
+                    Err(e) => handle(Err(e), 0)?,
+                };
+                if exit_reason.is_some() {
+                    break;
+                }
+            }
+        }
+        input => {
+            let mut reader: Box<dyn io::Read + Send> = match input {
+                Some(input) => Box::new(fs::File::open(input)?),
+                None => Box::new(io::stdin()),
+            };
+            // stop waiting for data once the timeout passes
+            if let Some(deadline) = deadline {
+                reader = Box::new(defmt_decoder::exit::read_with_deadline(reader, deadline));
+            }
+            for frame in Frames::from_images(tables.clone(), reader)? {
+                let frame = match frame {
+                    Ok(frame) => frame,
+                    Err(e) if e.kind() == io::ErrorKind::TimedOut => {
+                        exit_reason = Some(ExitReason::Timeout);
+                        break;
                     }
-                },
+                    Err(e) => return Err(e.into()),
+                };
+                exit_reason = match frame {
+                    Ok(frame) => {
+                        let image = tables
+                            .iter()
+                            .position(|table| Arc::ptr_eq(table, frame.table()))
+                            .unwrap();
+                        handle(Ok(&frame.frame()), image)?
+                    }
+                    Err(e) => handle(Err(e), 0)?,
+                };
+                if exit_reason.is_some() {
+                    break;
+                }
             }
         }
     }
+
+    if let Some(trace) = trace {
+        trace.finish()?;
+    }
+    // the input ended without any of the exit conditions being met
+    if exit_reason.is_none() {
+        exit_reason = exit_conditions.end();
+    }
+    if let Some(exit_reason) = exit_reason {
+        process::exit(exit_reason.code());
+    }
+    Ok(())
 }
 
-type LocationInfo = (Option<String>, Option<u32>, Option<String>);
+#[derive(Default)]
+struct LocationInfo {
+    /// The file as displayed, relative to the current directory if it is in it
+    file: Option<String>,
+    /// The absolute path of the file
+    path: Option<String>,
+    line: Option<u32>,
+    column: Option<u32>,
+    module_path: Option<String>,
+    function: Option<String>,
+}
 
 fn forward_to_logger(frame: &Frame, location_info: LocationInfo) {
-    let (file, line, mod_path) = location_info;
-    defmt_decoder::log::log_defmt(frame, file.as_deref(), line, mod_path.as_deref());
+    defmt_decoder::log::log_defmt_at(
+        frame,
+        &DefmtLocation {
+            file: location_info.file.as_deref(),
+            path: location_info.path.as_deref(),
+            line: location_info.line,
+            column: location_info.column,
+            module_path: location_info.module_path.as_deref(),
+            function: location_info.function.as_deref(),
+        },
+    );
 }
 
-fn location_info(locs: &Option<Locations>, frame: &Frame, current_dir: &Path) -> LocationInfo {
-    let (mut file, mut line, mut mod_path) = (None, None, None);
+fn location_info(
+    locs: &mut LazyLocations,
+    frame: &Frame,
+    current_dir: &Path,
+) -> anyhow::Result<LocationInfo> {
+    let Some(loc) = locs.get(frame.index())? else {
+        return Ok(LocationInfo::default());
+    };
+
+    // try to get the relative path, else the full one
+    let relative = loc.file.strip_prefix(current_dir).unwrap_or(&loc.file);
 
-    let loc = locs.as_ref().map(|locs| locs.get(&frame.index()));
+    Ok(LocationInfo {
+        file: Some(relative.display().to_string()),
+        path: Some(current_dir.join(&loc.file).display().to_string()),
+        line: Some(loc.line as u32),
+        column: loc.column.map(|column| column as u32),
+        module_path: Some(loc.module.clone()),
+        function: loc.function.clone(),
+    })
+}
 
-    if let Some(Some(loc)) = loc {
-        // try to get the relative path, else the full one
-        let path = loc.file.strip_prefix(current_dir).unwrap_or(&loc.file);
+/// Reads the ELF at `path` and parses its table.
+fn read_elf(path: &Path) -> anyhow::Result<(Vec<u8>, Table)> {
+    let bytes = fs::read(path)?;
+    let table = Table::parse(&bytes)?
+        .ok_or_else(|| anyhow!("{}: .defmt data not found", path.display()))?;
+    Ok((bytes, table))
+}
 
-        file = Some(path.display().to_string());
-        line = Some(loc.line as u32);
-        mod_path = Some(loc.module.clone());
+/// Returns the debug info to look up the locations of the log statements of the ELF at `path`
+/// in: the `debug_file` if one is given, else the file found by the build ID or `.gnu_debuglink`
+/// of the ELF, else the ELF itself.
+fn read_debug_info<'a>(
+    path: &Path,
+    bytes: &'a [u8],
+    debug_file: Option<PathBuf>,
+) -> anyhow::Result<Cow<'a, [u8]>> {
+    match debug_file.or_else(|| defmt_decoder::find_debug_file(bytes, path)) {
+        Some(debug_file) => Ok(Cow::Owned(fs::read(debug_file)?)),
+        None => Ok(Cow::Borrowed(bytes)),
     }
-
-    (file, line, mod_path)
 }
 
 /// Report version from Cargo.toml _(e.g. "0.1.4")_ and supported `defmt`-versions.
@@ -0,0 +1,170 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::bail;
+use defmt_decoder::{CatalogEntry, EntryDiff, Location, StringEntry};
+use serde_json::json;
+
+use crate::{read_debug_info, read_elf, CatalogFormat};
+
+/// Prints how the table changed between the `old` and the `new` build of the firmware.
+///
+/// Fails if the new build reuses an index for a different format string at the same location.
+pub(crate) fn diff(old: &Path, new: &Path) -> anyhow::Result<()> {
+    let (old_bytes, old_table) = read_elf(old)?;
+    let (new_bytes, new_table) = read_elf(new)?;
+    let old_locations = old_table.get_locations(&read_debug_info(old, &old_bytes, None)?)?;
+    let new_locations = new_table.get_locations(&read_debug_info(new, &new_bytes, None)?)?;
+    let diff = old_table
+        .diff(&new_table)
+        .with_locations(&old_locations, &new_locations);
+
+    let (mut unchanged, mut changed, mut added, mut removed) = (0, 0, 0, 0);
+    let mut incompatible = 0;
+    for (index, entry) in diff.entries() {
+        match entry {
+            EntryDiff::Unchanged(entry) => {
+                unchanged += 1;
+                println!("unchanged {index:#06x} {}", display(entry));
+            }
+            EntryDiff::Changed {
+                old,
+                new,
+                old_location,
+                new_location,
+            } => {
+                changed += 1;
+                if entry.is_incompatible() {
+                    incompatible += 1;
+                }
+                println!(
+                    "changed   {index:#06x} {} ({}) -> {} ({})",
+                    display(old),
+                    display_location(old_location),
+                    display(new),
+                    display_location(new_location),
+                );
+            }
+            EntryDiff::Added(entry) => {
+                added += 1;
+                println!("added     {index:#06x} {}", display(entry));
+            }
+            EntryDiff::Removed(entry) => {
+                removed += 1;
+                println!("removed   {index:#06x} {}", display(entry));
+            }
+        }
+    }
+    println!("{unchanged} unchanged, {changed} changed, {added} added, {removed} removed");
+
+    if !diff.is_compatible() {
+        bail!("the new build reuses {incompatible} indices for different format strings at the same location; keep the old ELF to decode the logs of devices running it");
+    }
+    Ok(())
+}
+
+/// Prints the log and `println!` statements of the firmware, for its documentation.
+pub(crate) fn catalog(
+    elf: &Path,
+    debug_file: Option<PathBuf>,
+    format: CatalogFormat,
+) -> anyhow::Result<()> {
+    let (bytes, table) = read_elf(elf)?;
+    let locs = table.get_locations(&read_debug_info(elf, &bytes, debug_file)?)?;
+    let catalog = table.catalog(&locs);
+
+    match format {
+        CatalogFormat::Json => {
+            let entries = catalog
+                .iter()
+                .map(|entry| {
+                    json!({
+                        "index": entry.index,
+                        "level": level(entry),
+                        "format": entry.entry.string(),
+                        "args": entry.args,
+                        "file": entry.location.map(|loc| loc.file.display().to_string()),
+                        "line": entry.location.map(|loc| loc.line),
+                        "module": entry.location.map(|loc| &loc.module),
+                    })
+                })
+                .collect::<Vec<_>>();
+            println!("{}", serde_json::to_string_pretty(&entries)?);
+        }
+        CatalogFormat::Markdown => {
+            let mut module = None;
+            for entry in &catalog {
+                let entry_module = entry.location.map(|loc| &*loc.module);
+                if module != Some(entry_module) {
+                    if module.is_some() {
+                        println!();
+                    }
+                    module = Some(entry_module);
+                    println!("## {}", entry_module.unwrap_or("Unknown location"));
+                    println!();
+                    println!("| Index | Level | Format | Arguments | Location |");
+                    println!("| --- | --- | --- | --- | --- |");
+                }
+
+                println!(
+                    "| {:#06x} | {} | {} | {} | {} |",
+                    entry.index,
+                    level(entry),
+                    markdown_code(entry.entry.string()),
+                    entry
+                        .args
+                        .iter()
+                        .map(|arg| markdown_code(arg))
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    entry.location.map_or(String::new(), |loc| format!(
+                        "{}:{}",
+                        loc.file.display(),
+                        loc.line
+                    )),
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn level(entry: &CatalogEntry) -> String {
+    format!("{:?}", entry.entry.tag()).to_lowercase()
+}
+
+/// Formats `s` as inline code in a table cell, delimited by more backticks than `s` has in a row.
+fn markdown_code(s: &str) -> String {
+    let backticks = s.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(backticks + 1);
+    // a backtick at the start or end of `s` would otherwise be taken as part of the fence
+    let padding = match s.starts_with('`') || s.ends_with('`') {
+        true => " ",
+        false => "",
+    };
+    format!("{fence}{padding}{}{padding}{fence}", s.replace('|', "\\|"))
+}
+
+fn display(entry: &StringEntry) -> String {
+    format!("{:?} {:?}", entry.tag(), entry.string())
+}
+
+fn display_location(location: Option<&Location>) -> String {
+    location.map_or("unknown location".to_owned(), |loc| {
+        format!("{}:{}", loc.file.display(), loc.line)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn markdown_code() {
+        assert_eq!(super::markdown_code("x={=u8}"), "`x={=u8}`");
+        assert_eq!(super::markdown_code("a|b"), "`a\\|b`");
+        assert_eq!(
+            super::markdown_code("run `cargo` ``x``"),
+            "``` run `cargo` ``x`` ```"
+        );
+        assert_eq!(super::markdown_code("`x`"), "`` `x` ``");
+    }
+}
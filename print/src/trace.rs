@@ -0,0 +1,175 @@
+//! Export of decoded frames in the Chrome Trace Event format, as understood by Perfetto and
+//! `chrome://tracing`.
+//!
+//! Every frame becomes an instant event on a track (a "thread") named after the module path of its
+//! log statement. Frames whose message starts with `begin ` or `end ` open and close a duration
+//! slice named after the rest of the message, e.g. `defmt::info!("begin init")` and
+//! `defmt::info!("end init")`.
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use defmt_decoder::Frame;
+use serde_json::{json, Value};
+
+const BEGIN_PREFIX: &str = "begin ";
+const END_PREFIX: &str = "end ";
+const UNKNOWN_TRACK: &str = "<unknown>";
+
+pub(crate) struct ChromeTrace<W: Write = BufWriter<File>> {
+    sink: W,
+    /// Maps module paths to track ids.
+    tracks: HashMap<String, usize>,
+    is_first: bool,
+    /// Number of frames written so far; used as the timestamp if the firmware has none.
+    frames: u64,
+}
+
+impl ChromeTrace {
+    pub(crate) fn create(path: &Path) -> io::Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?))
+    }
+}
+
+impl<W: Write> ChromeTrace<W> {
+    fn new(mut sink: W) -> io::Result<Self> {
+        sink.write_all(b"[\n")?;
+
+        Ok(Self {
+            sink,
+            tracks: HashMap::new(),
+            is_first: true,
+            frames: 0,
+        })
+    }
+
+    /// Appends `frame` to the trace, on the track of `module_path`.
+    pub(crate) fn frame(&mut self, frame: &Frame, module_path: Option<&str>) -> io::Result<()> {
+        let track = module_path.unwrap_or(UNKNOWN_TRACK);
+        let tid = match self.tracks.get(track) {
+            Some(tid) => *tid,
+            None => {
+                let tid = self.tracks.len();
+                self.tracks.insert(track.to_string(), tid);
+                self.event(json!({
+                    "name": "thread_name",
+                    "ph": "M",
+                    "pid": 0,
+                    "tid": tid,
+                    "args": { "name": track },
+                }))?;
+                tid
+            }
+        };
+
+        let ts = frame
+            .timestamp()
+            .map(|ts| u64::try_from(ts).unwrap_or(u64::MAX))
+            .unwrap_or(self.frames);
+        self.frames += 1;
+
+        let message = frame.display_message().to_string();
+        let (name, phase) = if let Some(name) = message.strip_prefix(BEGIN_PREFIX) {
+            (name, "B")
+        } else if let Some(name) = message.strip_prefix(END_PREFIX) {
+            (name, "E")
+        } else {
+            (&*message, "i")
+        };
+
+        let mut event = json!({
+            "name": name,
+            "cat": "defmt",
+            "ph": phase,
+            "ts": ts,
+            "pid": 0,
+            "tid": tid,
+            "args": {
+                "index": frame.index(),
+                "level": frame.level().map(|level| level.as_str()),
+            },
+        });
+        if phase == "i" {
+            // scope the instant event to its track
+            event["s"] = "t".into();
+        }
+
+        self.event(event)
+    }
+
+    /// Terminates the JSON array and flushes the file.
+    pub(crate) fn finish(mut self) -> io::Result<W> {
+        self.sink.write_all(b"\n]\n")?;
+        self.sink.flush()?;
+        Ok(self.sink)
+    }
+
+    fn event(&mut self, event: Value) -> io::Result<()> {
+        if !self.is_first {
+            self.sink.write_all(b",\n")?;
+        }
+        self.is_first = false;
+        serde_json::to_writer(&mut self.sink, &event)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use defmt_decoder::{Encoding, StringEntry, Table, TableEntry, Tag};
+
+    use super::*;
+
+    #[test]
+    fn trace() {
+        let entries = [
+            (Tag::Info, "begin init"),
+            (Tag::Warn, "end init"),
+            (Tag::Println, "tick"),
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(index, (tag, format))| {
+            let entry = TableEntry::new(StringEntry::new(tag, format.into()), "".into());
+            (index, entry)
+        })
+        .collect::<BTreeMap<_, _>>();
+        let table = Table::new(entries, Encoding::Raw);
+
+        let mut trace = ChromeTrace::new(Vec::new()).unwrap();
+        let (begin, _) = table.decode(&[0, 0]).unwrap();
+        trace.frame(&begin, Some("app::init")).unwrap();
+        let (end, _) = table.decode(&[1, 0]).unwrap();
+        trace.frame(&end, Some("app::init")).unwrap();
+        let (tick, _) = table.decode(&[2, 0]).unwrap();
+        trace.frame(&tick, None).unwrap();
+        let out = trace.finish().unwrap();
+
+        let events: Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(
+            events,
+            json!([
+                { "name": "thread_name", "ph": "M", "pid": 0, "tid": 0, "args": { "name": "app::init" } },
+                {
+                    "name": "init", "cat": "defmt", "ph": "B", "ts": 0, "pid": 0, "tid": 0,
+                    "args": { "index": 0, "level": "info" },
+                },
+                {
+                    "name": "init", "cat": "defmt", "ph": "E", "ts": 1, "pid": 0, "tid": 0,
+                    "args": { "index": 1, "level": "warn" },
+                },
+                { "name": "thread_name", "ph": "M", "pid": 0, "tid": 1, "args": { "name": "<unknown>" } },
+                {
+                    "name": "tick", "cat": "defmt", "ph": "i", "ts": 2, "pid": 0, "tid": 1,
+                    "args": { "index": 2, "level": null }, "s": "t",
+                },
+            ])
+        );
+    }
+}
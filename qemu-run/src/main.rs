@@ -4,14 +4,26 @@
 //! *Printers* are *host* programs that receive log data, format it and display it.
 
 use std::{
-    env, fs,
-    io::Read as _,
+    env, fs, io,
     process::{self, Command, Stdio},
+    sync::Arc,
+    time::Duration,
 };
 
-use anyhow::{anyhow, bail};
-use defmt_decoder::{DecodeError, StreamDecoder, Table};
+use anyhow::{anyhow, bail, Context as _};
+use defmt_decoder::{
+    exit::{self, ExitConditions, ExitReason},
+    Table,
+};
 use process::Child;
+use regex::Regex;
+
+const SYNTAX: &str = "`qemu-run [--exit-on-match <regex>] [--fail-on-level <level>] \
+    [--timeout <secs>] [--max-frames <n>] <path-to-elf>`";
+
+/// Exit code when QEMU fails by itself, outside of the codes that `ExitReason`s and decoding
+/// errors use.
+const QEMU_FAILED: i32 = 6;
 
 fn main() -> Result<(), anyhow::Error> {
     notmain().map(|opt_code| {
@@ -22,14 +34,33 @@ fn main() -> Result<(), anyhow::Error> {
 }
 
 fn notmain() -> Result<Option<i32>, anyhow::Error> {
-    let args = env::args().skip(1 /* program name */).collect::<Vec<_>>();
-
-    if args.len() != 1 {
-        bail!("expected exactly one argument. Syntax: `qemu-run <path-to-elf>`");
+    let mut args = env::args().skip(1 /* program name */);
+    let mut path = None;
+    let mut exit_conditions = ExitConditions::new();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| anyhow!("`{arg}` expects a value. Syntax: {SYNTAX}"))
+        };
+        exit_conditions = match arg.as_str() {
+            "--exit-on-match" => exit_conditions.exit_on_match(Regex::new(&value()?)?),
+            "--fail-on-level" => exit_conditions.fail_on_level(exit::parse_level(&value()?)?),
+            "--timeout" => exit_conditions.timeout(Duration::from_secs(
+                value()?.parse().context("invalid `--timeout`")?,
+            )),
+            "--max-frames" => {
+                exit_conditions.max_frames(value()?.parse().context("invalid `--max-frames`")?)
+            }
+            _ if path.is_none() && !arg.starts_with("--") => {
+                path = Some(arg);
+                exit_conditions
+            }
+            _ => bail!("unexpected argument `{arg}`. Syntax: {SYNTAX}"),
+        };
     }
+    let path = path.ok_or_else(|| anyhow!("expected the path to an ELF. Syntax: {SYNTAX}"))?;
 
-    let path = &args[0];
-    let bytes = fs::read(path)?;
+    let bytes = fs::read(&path)?;
 
     let table = if env::var_os("QEMU_RUN_IGNORE_VERSION").is_some() {
         Table::parse_ignore_version(&bytes)
@@ -52,55 +83,57 @@ fn notmain() -> Result<Option<i32>, anyhow::Error> {
                 "enable=on,target=native",
                 "-kernel",
             ])
-            .arg(path)
+            .arg(&path)
             .stdout(Stdio::piped())
             .spawn()
             .expect("Error running qemu-system-arm; perhaps you haven't installed it yet?"),
     );
 
-    let mut stdout = child
+    let stdout = child
         .0
         .stdout
         .take()
         .ok_or_else(|| anyhow!("failed to acquire child's stdout handle"))?;
 
-    let mut decoder = table.new_stream_decoder();
-
-    let mut readbuf = [0; 256];
-    let exit_code;
-    loop {
-        let n = stdout.read(&mut readbuf)?;
-        decoder.received(&readbuf[..n]);
-        decode(&mut *decoder)?;
-
-        if let Some(status) = child.0.try_wait()? {
-            exit_code = status.code();
-
-            let mut data = Vec::new();
-            stdout.read_to_end(&mut data)?;
-            decoder.received(&data);
-            decode(&mut *decoder)?;
-
-            break;
-        }
-    }
-
-    Ok(exit_code)
-}
+    // stop waiting for QEMU once the timeout passes
+    let stdout: Box<dyn io::Read> = match exit_conditions.deadline() {
+        Some(deadline) => Box::new(exit::read_with_deadline(stdout, deadline)),
+        None => Box::new(stdout),
+    };
 
-fn decode(decoder: &mut dyn StreamDecoder) -> Result<(), DecodeError> {
-    loop {
-        match decoder.decode() {
+    // ends when QEMU exits and closes its stdout
+    for frame in Arc::new(table).frames(stdout) {
+        let frame = match frame {
+            Ok(frame) => frame,
+            Err(e) if e.kind() == io::ErrorKind::TimedOut => {
+                return Ok(Some(ExitReason::Timeout.code()))
+            }
+            Err(e) => return Err(e.into()),
+        };
+        match frame {
             Ok(frame) => {
-                println!("{}", frame.display(true))
+                let frame = frame.frame();
+                println!("{}", frame.display(true));
+                // `child` kills QEMU when it's dropped
+                if let Some(reason) = exit_conditions.check(&frame) {
+                    return Ok(Some(reason.code()));
+                }
             }
-            Err(DecodeError::UnexpectedEof) => return Ok(()),
-            Err(DecodeError::Malformed) => {
+            Err(e) => {
                 eprintln!("failed to decode defmt data");
-                return Err(DecodeError::Malformed);
+                return Err(e.into());
             }
         }
     }
+
+    let status = child.0.wait()?;
+    if !status.success() {
+        // QEMU's own exit code could be mistaken for one of ours
+        eprintln!("QEMU failed ({status})");
+        return Ok(Some(QEMU_FAILED));
+    }
+    // QEMU exited successfully without any of the exit conditions being met
+    Ok(exit_conditions.end().map(ExitReason::code))
 }
 
 struct KillOnDrop(Child);
@@ -202,6 +202,84 @@ fn test_host(deny_warnings: bool) {
         },
         "host",
     );
+
+    do_test(
+        || {
+            run_command(
+                "cargo",
+                &[
+                    "test",
+                    "-p",
+                    "defmt",
+                    "--features",
+                    "unstable-test,encoding-varint,image-id",
+                ],
+                None,
+                &[],
+            )
+        },
+        "host",
+    );
+
+    do_test(
+        || {
+            run_command(
+                "cargo",
+                &["test", "-p", "defmt", "--features", "unstable-test,image-id"],
+                None,
+                &[],
+            )
+        },
+        "host",
+    );
+
+    do_test(
+        || {
+            run_command(
+                "cargo",
+                &["test", "-p", "defmt", "--features", "unstable-test,encoding-lzss"],
+                None,
+                &[],
+            )
+        },
+        "host",
+    );
+
+    do_test(
+        || {
+            run_command(
+                "cargo",
+                &[
+                    "test",
+                    "-p",
+                    "defmt",
+                    "--features",
+                    "unstable-test,encoding-rzcobs-crc",
+                ],
+                None,
+                &[],
+            )
+        },
+        "host",
+    );
+
+    do_test(
+        || {
+            run_command(
+                "cargo",
+                &[
+                    "test",
+                    "-p",
+                    "defmt-decoder",
+                    "--features",
+                    "unstable,async,tracing,exit",
+                ],
+                None,
+                &[],
+            )
+        },
+        "host",
+    );
 }
 
 fn test_cross() {